@@ -0,0 +1,87 @@
+/*
+ * egypt-solver の C API
+ *
+ * src/ffi.rs の宣言に合わせて書いている(tests/ffi.rs で関数の型と定数の値が一致することを確認する)。
+ * cbindgen があれば "cbindgen --config cbindgen.toml --output include/egypt.h" で作り直せる。
+ *
+ * 手はセルの番号(8*y + x)で表す。失敗した関数は NULL や負の値を返し、
+ * その理由は egypt_last_error() で得られる。内部で panic した場合も同様。
+ */
+
+#ifndef EGYPT_H
+#define EGYPT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define EGYPT_SOLVED 0
+#define EGYPT_UNSOLVED 1
+#define EGYPT_LIMIT 2
+
+/* 局面 */
+typedef struct EgyptBoard EgyptBoard;
+
+/* solve の結果。解は回転数、歩数の少ない順 */
+typedef struct EgyptResult EgyptResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* このスレッドで最後に起きたエラーの説明。無ければ NULL */
+const char *egypt_last_error(void);
+
+/* テキスト形式の局面を読む。失敗したら NULL */
+EgyptBoard *egypt_board_parse(const char *text);
+
+EgyptBoard *egypt_board_clone(const EgyptBoard *board);
+
+void egypt_board_free(EgyptBoard *board);
+
+/* テキスト形式の局面。egypt_string_free() で解放する */
+char *egypt_board_to_string(const EgyptBoard *board);
+
+void egypt_string_free(char *s);
+
+/* プレイヤーの位置(セルの番号) */
+uint8_t egypt_board_pos(const EgyptBoard *board);
+
+/* 打てる手を最大 cap 個 out に書き、手の総数を返す。out が NULL なら総数だけ返す */
+size_t egypt_board_moves(const EgyptBoard *board, uint8_t *out, size_t cap);
+
+/* 手を打つ。打てない手なら局面は変えずに -1、打てたらその手の歩数を返す */
+int egypt_board_apply(EgyptBoard *board, uint8_t pos);
+
+int egypt_board_is_solved(const EgyptBoard *board);
+
+/* 解けておらず、これ以上解ける見込みが無ければ 1(打てる手が無い場合も含む) */
+int egypt_board_is_stuck(const EgyptBoard *board);
+
+/* 解くのに必要な回転数の下界。解けていれば 0、行き詰まっていれば -1 */
+int egypt_board_least_to_solve(const EgyptBoard *board);
+
+/* "egypt solve" と同じオプション(空白区切り、NULL なら既定)で解く。オプションが誤っていれば NULL */
+EgyptResult *egypt_solve(const EgyptBoard *board, const char *options);
+
+void egypt_result_free(EgyptResult *result);
+
+/* EGYPT_SOLVED, EGYPT_UNSOLVED, EGYPT_LIMIT のいずれか */
+int egypt_result_status(const EgyptResult *result);
+
+/* 解の数 */
+size_t egypt_result_count(const EgyptResult *result);
+
+/* i 番目の解の手を最大 cap 個 out に書き、解の手数(回転数)を返す。i が範囲外なら 0 */
+size_t egypt_result_solution(const EgyptResult *result, size_t i, uint8_t *out, size_t cap);
+
+/* i 番目の解の歩数。i が範囲外なら 0 */
+uint32_t egypt_result_step(const EgyptResult *result, size_t i);
+
+/* 調べたノード数。数えないソルバーなら -1 */
+int64_t egypt_result_nodes(const EgyptResult *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* EGYPT_H */
@@ -0,0 +1,391 @@
+/*!
+ * アルゴリズム名とオプションからソルバーを作る
+ *
+ * コマンドラインの "solve --algo <name>" で使う。オプションは SOLVE_OPTIONS の通りで、
+ * 各アルゴリズムのデフォルト値もここで決める。
+ *
+ * 解のキャッシュ(cache::CachedSolver)は、--cache か --cache-dir があれば通す。
+ * "egypt solve" だけは build_solver_with() で既定でも通し、--no-cache で使わない。
+ * 最短解を全て返す設定のときだけ結果をキャッシュに記録する。
+ */
+
+use std::fs;
+use std::path::{ PathBuf };
+use std::thread;
+use std::time;
+
+use failure;
+
+use ::{ Cancel, Solver };
+use anneal::{ AnnealSolver, Schedule };
+use beam::{ BeamSolver, BeamWeights };
+use bfs::{ BfsSolver };
+use bfs_disk::{ DiskBfsSolver };
+use bnb::{ BnbSolver };
+use cache::{ Cache, CachedSolver };
+use checkpoint::{ Checkpoint };
+use cli::{ Args, OptSpec };
+use genetic::{ Crossover, Encoding, GeneticConfig, GeneticSolver, Mutation, Selection, WeightedFitness };
+use ida::{ IdaSolver };
+use iddfs::{ IddfsSolver };
+use island::{ IslandSolver, Topology };
+use nmcs::{ NmcsSolver };
+use population::{ Population };
+use util;
+
+pub const ALGOS: &[&str] = &["bfs", "iddfs", "ida", "beam", "bnb", "genetic", "anneal", "nmcs"];
+
+/// 何も指定しなかったときのアルゴリズム
+pub const ALGO_DEFAULT: &str = "ida";
+
+macro_rules! opt {
+    ($name:expr, $value:expr, $help:expr, [$($algo:expr),*]) => {
+        OptSpec { name: $name, value: $value, help: $help, algos: &[$($algo),*] }
+    };
+}
+
+pub const SOLVE_OPTIONS: &[OptSpec] = &[
+    opt!("algo",            Some("name"),       "bfs|iddfs|ida|beam|bnb|genetic|anneal|nmcs (default: ida)", []),
+    opt!("threads",         Some("n"),          "number of threads (default: all cores)", ["bfs", "iddfs", "ida", "bnb"]),
+    opt!("max-nodes",       Some("n"),          "give up after n nodes", ["bfs", "iddfs", "ida", "bnb"]),
+    opt!("disk",            Some("dir"),        "keep BFS layers on disk under dir", ["bfs"]),
+    opt!("max-depth-ini",   Some("n"),          "initial depth limit (default: 0)", ["iddfs", "ida"]),
+    opt!("max-depth",       Some("n"),          "give up beyond depth n (beam default: 100)", ["iddfs", "ida", "beam"]),
+    opt!("reduce",          None,               "skip reordered commuting moves", ["iddfs", "ida"]),
+    opt!("expand",          None,               "with --reduce, still report every ordering", ["iddfs", "ida"]),
+    opt!("checkpoint",      Some("file"),       "save progress to file every minute", ["iddfs", "ida"]),
+    opt!("resume",          Some("file"),       "resume from a checkpoint (the board is read from it)", ["iddfs", "ida"]),
+    opt!("width",           Some("n"),          "beam width (default: 1000)", ["beam", "bnb"]),
+    opt!("beam-weights",    Some("l,p,r,s"),    "least,pieces,rotate,step weights (default: 1,2,0,0)", ["beam"]),
+    opt!("seed",            Some("n"),          "random seed (default: random)", ["genetic", "anneal", "nmcs"]),
+    opt!("max-len",         Some("n"),          "longest sequence to consider (required for genetic/anneal; nmcs default: 100)", ["genetic", "anneal", "nmcs"]),
+    opt!("generations",     Some("n"),          "number of generations (default: 1000)", ["genetic"]),
+    opt!("population",      Some("n"),          "number of candidates (default: 1000)", ["genetic"]),
+    opt!("elite",           Some("n"),          "number of elites (default: 10)", ["genetic"]),
+    opt!("op-weights",      Some("c,x,m"),      "copy,crossover,mutation weights (default: 9,90,1)", ["genetic"]),
+    opt!("crossover",       Some("kind"),       "onepoint|twopoint|uniform (default: twopoint)", ["genetic"]),
+    opt!("mutation",        Some("kind"),       "point|swap|insert|delete (default: point)", ["genetic"]),
+    opt!("selection",       Some("kind"),       "roulette|tournament (default: roulette)", ["genetic"]),
+    opt!("tournament-size", Some("n"),          "tournament size (default: 3)", ["genetic"]),
+    opt!("memetic",         None,               "improve elite candidates by local search", ["genetic"]),
+    opt!("tail-depth",      Some("n"),          "IDA* depth for local search (default: 4)", ["genetic"]),
+    opt!("fitness",         Some("kind"),       "pieces|weighted (default: pieces)", ["genetic"]),
+    opt!("encoding",        Some("kind"),       "relative|absolute (default: relative)", ["genetic"]),
+    opt!("islands",         Some("n"),          "number of islands, one thread each (default: 1)", ["genetic"]),
+    opt!("migration",       Some("interval,n"), "migrate n elites every interval generations (default: 10,10)", ["genetic"]),
+    opt!("topology",        Some("kind"),       "ring|complete (default: ring)", ["genetic"]),
+    opt!("log",             Some("file"),       "write per-generation statistics", ["genetic"]),
+    opt!("load",            Some("file"),       "start from a saved population", ["genetic"]),
+    opt!("init-solutions",  Some("file"),       "add known solutions to the initial population", ["genetic"]),
+    opt!("save",            Some("file"),       "save the final population", ["genetic"]),
+    opt!("time",            Some("seconds"),    "time budget (default: 10)", ["anneal"]),
+    opt!("temp",            Some("start,end"),  "temperature range (default: 2000,10)", ["anneal"]),
+    opt!("linear",          None,               "linear instead of geometric cooling", ["anneal"]),
+    opt!("level",           Some("n"),          "nesting level (default: 2)", ["nmcs"]),
+    opt!("iterations",      Some("n"),          "number of restarts (default: 10)", ["nmcs"]),
+    opt!("cache",           None,               "use the solution cache (default for solve only)", []),
+    opt!("cache-dir",       Some("dir"),        "solution cache directory (default: $EGYPT_CACHE_DIR or ~/.cache/egypt)", []),
+    opt!("no-cache",        None,               "neither read nor write the solution cache", []),
+];
+
+/// キャッシュに記録する制限
+const LIMIT_OPTIONS: &[&str] = &["max-nodes", "max-depth"];
+
+/// キャッシュのディレクトリ。--no-cache なら None
+///
+/// by_default が偽なら、--cache か --cache-dir が無いときも None。
+pub fn cache_dir(args: &Args, by_default: bool) -> Option<PathBuf> {
+    if args.flag("no-cache") { return None; }
+    if let Some(dir) = args.value("cache-dir") { return Some(PathBuf::from(dir)); }
+    if by_default || args.flag("cache") { Cache::default_dir() } else { None }
+}
+
+// 最短解を全て返す設定か(そのときだけ結果をキャッシュに記録する)
+fn is_exhaustive(args: &Args, algo: &str) -> bool {
+    match algo {
+        "bfs" | "bnb"   => true,
+        // 初期深さが最短より深いと最短でない解を返しうる
+        "iddfs" | "ida" => args.value("max-depth-ini").is_none() && (!args.flag("reduce") || args.flag("expand")),
+        _               => false,
+    }
+}
+
+/// 選んだアルゴリズム。--algo が無ければ ALGO_DEFAULT
+pub fn algo_name(args: &Args) -> Result<&str, failure::Error> {
+    let algo = args.value("algo").unwrap_or(ALGO_DEFAULT);
+    if !ALGOS.contains(&algo) {
+        bail!("unknown algorithm: {} (one of {})", algo, ALGOS.join(", "));
+    }
+    Ok(algo)
+}
+
+/// args の指定通りにソルバーを作る
+///
+/// 使われないオプションが指定されていたらエラーにする。
+pub fn build_solver(args: &Args) -> Result<Box<dyn Solver + Send>, failure::Error> {
+    build_solver_with(args, &Cancel::new(), false)
+}
+
+/// build_solver() と同じだが、cancel で探索を中断できるソルバーを作る
+pub fn build_solver_with_cancel(args: &Args, cancel: &Cancel) -> Result<Box<dyn Solver + Send>, failure::Error> {
+    build_solver_with(args, cancel, false)
+}
+
+/// build_solver_with_cancel() と同じだが、cache が真なら --cache が無くてもキャッシュを使う
+pub fn build_solver_with(args: &Args, cancel: &Cancel, cache: bool) -> Result<Box<dyn Solver + Send>, failure::Error> {
+    const CHECKPOINT_INTERVAL: u64 = 60;
+    let algo = algo_name(args)?;
+    let unused = args.unused(SOLVE_OPTIONS, algo);
+    if !unused.is_empty() {
+        bail!("--{} cannot be used with --algo {}", unused.join(", --"), algo);
+    }
+
+    let n_thread = args.get_or("threads", thread::available_parallelism().map(|n| n.get()).unwrap_or(1))?;
+    if n_thread == 0 { bail!("--threads must be positive"); }
+    let max_len = || -> Result<u32, failure::Error> {
+        match args.get("max-len")? {
+            Some(0)       => bail!("--max-len must be positive"),
+            Some(max_len) => Ok(max_len),
+            None          => bail!("--max-len is required for --algo {}", algo),
+        }
+    };
+
+    let solver: Box<dyn Solver + Send> = match algo {
+        "bfs" => match args.value("disk") {
+            Some(dir) => Box::new(DiskBfsSolver::new(dir, args.get_or("max-nodes", 1_000_000_000_000)?)
+                .cancel(cancel.clone())),
+            None      => Box::new(BfsSolver::new(args.get_or("max-nodes", 10_000_000)?)
+                .threads(n_thread)
+                .cancel(cancel.clone())),
+        },
+        "iddfs" | "ida" => {
+            let max_depth_ini  = args.get_or("max-depth-ini", 0)?;
+            let max_node_count = args.get_or("max-nodes", 1_000_000_000_000_000_000)?;
+            let expand = args.flag("expand");
+            if expand && !args.flag("reduce") { bail!("--expand needs --reduce"); }
+
+            // IdaSolver と IddfsSolver は同じビルダーを持つ
+            macro_rules! configure {
+                ($solver:expr) => {{
+                    let mut solver = $solver.threads(n_thread).cancel(cancel.clone());
+                    if let Some(depth) = args.get("max-depth")? {
+                        solver = solver.max_depth(depth);
+                    }
+                    if args.flag("reduce") {
+                        solver = solver.reduce_order(expand);
+                    }
+                    // 再開時は特に指定が無ければ同じファイルに保存する
+                    let mut checkpoint = args.value("checkpoint");
+                    if let Some(path) = args.value("resume") {
+                        solver = solver.resume(Checkpoint::load(path)?);
+                        checkpoint = checkpoint.or(Some(path));
+                    }
+                    if let Some(path) = checkpoint {
+                        solver = solver.checkpoint(path, time::Duration::from_secs(CHECKPOINT_INTERVAL));
+                    }
+                    Box::new(solver)
+                }};
+            }
+            if algo == "ida" {
+                configure!(IdaSolver::new(max_depth_ini, max_node_count))
+            }
+            else {
+                configure!(IddfsSolver::new(max_depth_ini, max_node_count))
+            }
+        },
+        "beam" => {
+            let width = args.get_or("width", 1000)?;
+            if width == 0 { bail!("--width must be positive"); }
+            let mut solver = BeamSolver::new(width, args.get_or("max-depth", 100)?).cancel(cancel.clone());
+            if let Some(w) = args.get_list("beam-weights", 4)? {
+                solver = solver.weights(BeamWeights {
+                    least:  w[0],
+                    pieces: w[1],
+                    rotate: w[2],
+                    step:   w[3],
+                });
+            }
+            Box::new(solver)
+        },
+        "bnb" => {
+            const BEAM_MAX_DEPTH: u32 = 100;
+            let width = args.get_or("width", 1000)?;
+            if width == 0 { bail!("--width must be positive"); }
+            Box::new(BnbSolver::new(args.get_or("max-nodes", 1_000_000_000_000_000_000)?)
+                .upper_bound_solver(BeamSolver::new(width, BEAM_MAX_DEPTH).cancel(cancel.clone()))
+                .threads(n_thread)
+                .cancel(cancel.clone()))
+        },
+        "genetic" => build_genetic(args, max_len()?, cancel)?,
+        "anneal" => {
+            let seconds: f64 = args.get_or("time", 10.0)?;
+            let mut schedule = Schedule::default();
+            if let Some(v) = args.get_list("temp", 2)? {
+                schedule = Schedule::Geometric { start: v[0], end: v[1] };
+            }
+            if args.flag("linear") {
+                schedule = match schedule {
+                    Schedule::Geometric { start, end } | Schedule::Linear { start, end } => Schedule::Linear { start, end },
+                };
+            }
+            let mut solver = AnnealSolver::new(max_len()?, time::Duration::from_millis((seconds * 1000.0) as u64))
+                .schedule(schedule)
+                .cancel(cancel.clone());
+            if let Some(seed) = args.get("seed")? {
+                solver = solver.seed(seed);
+            }
+            eprintln!("seed: {}", solver.get_seed());
+            Box::new(solver)
+        },
+        "nmcs" => {
+            let mut solver = NmcsSolver::new(args.get_or("level", 2)?, args.get_or("iterations", 10)?)
+                .max_len(args.get_or("max-len", 100)?)
+                .cancel(cancel.clone());
+            if let Some(seed) = args.get("seed")? {
+                solver = solver.seed(seed);
+            }
+            eprintln!("seed: {}", solver.get_seed());
+            Box::new(solver)
+        },
+        _ => unreachable!(),
+    };
+
+    match cache_dir(args, cache) {
+        Some(dir) => {
+            let mut limits = LIMIT_OPTIONS.iter()
+                .filter_map(|name| args.value(name).map(|value| format!("{}={}", name, value)))
+                .collect::<Vec<_>>()
+                .join(" ");
+            if limits.is_empty() {
+                limits = "default".to_owned();
+            }
+            Ok(Box::new(CachedSolver::new(solver, Cache::new(dir), algo, &limits, is_exhaustive(args, algo))))
+        },
+        None => Ok(solver),
+    }
+}
+
+fn build_genetic(args: &Args, max_len: u32, cancel: &Cancel) -> Result<Box<dyn Solver + Send>, failure::Error> {
+    let mut config = GeneticConfig::default();
+    config.n_cand  = args.get_or("population", config.n_cand)?;
+    config.n_elite = args.get_or("elite", config.n_elite)?;
+    if let Some(w) = args.get_list::<u32>("op-weights", 3)? {
+        if w.iter().sum::<u32>() == 0 { bail!("--op-weights must not be all zero"); }
+        config.weight_copy      = w[0];
+        config.weight_crossover = w[1];
+        config.weight_mutate    = w[2];
+    }
+    if let Some(kind) = args.value("crossover") {
+        config.crossover = match kind {
+            "onepoint" => Crossover::OnePoint,
+            "twopoint" => Crossover::TwoPoint,
+            "uniform"  => Crossover::Uniform,
+            _          => bail!("unknown crossover: {}", kind),
+        };
+    }
+    if let Some(kind) = args.value("mutation") {
+        config.mutation = match kind {
+            "point"  => Mutation::Point,
+            "swap"   => Mutation::Swap,
+            "insert" => Mutation::Insert,
+            "delete" => Mutation::Delete,
+            _        => bail!("unknown mutation: {}", kind),
+        };
+    }
+    let tournament_size = args.get_or("tournament-size", 3)?;
+    if tournament_size == 0 { bail!("--tournament-size must be positive"); }
+    if let Some(kind) = args.value("selection") {
+        config.selection = match kind {
+            "roulette"   => Selection::Roulette,
+            "tournament" => Selection::Tournament(tournament_size),
+            _            => bail!("unknown selection: {}", kind),
+        };
+    }
+    config.memetic    = args.flag("memetic");
+    config.tail_depth = args.get_or("tail-depth", config.tail_depth)?;
+    if let Some(kind) = args.value("encoding") {
+        config.encoding = match kind {
+            "relative" => Encoding::Relative,
+            "absolute" => Encoding::Absolute,
+            _          => bail!("unknown encoding: {}", kind),
+        };
+    }
+    if config.n_cand == 0 || config.n_elite > config.n_cand {
+        bail!("--population must be positive and at least --elite");
+    }
+    let weighted = match args.value("fitness") {
+        Some("pieces") | None => false,
+        Some("weighted")      => true,
+        Some(kind)            => bail!("unknown fitness: {}", kind),
+    };
+
+    let n_gene = args.get_or("generations", 1000)?;
+    let pop_ini = match args.value("load") {
+        Some(path) => Some(Population::load(path)?),
+        None       => None,
+    };
+    let sol_ini = match args.value("init-solutions") {
+        Some(path) => util::parse_solutions(&fs::read_to_string(path)?)?,
+        None       => vec![],
+    };
+
+    let n_island = args.get_or("islands", 1)?;
+    if n_island == 0 { bail!("--islands must be positive"); }
+    if n_island > 1 {
+        let (interval, n_migrant) = match args.get_list::<usize>("migration", 2)? {
+            Some(v) => (v[0] as u32, v[1]),
+            None    => (10, 10),
+        };
+        if interval == 0 { bail!("--migration interval must be positive"); }
+        let topology = match args.value("topology") {
+            Some("ring") | None => Topology::Ring,
+            Some("complete")    => Topology::Complete,
+            Some(kind)          => bail!("unknown topology: {}", kind),
+        };
+        if args.value("log").is_some() { bail!("--log cannot be used with --islands"); }
+
+        let mut solver = IslandSolver::new(max_len, n_gene, n_island)
+            .config(config)
+            .migration(interval, n_migrant)
+            .topology(topology)
+            .initial_solutions(sol_ini)
+            .cancel(cancel.clone());
+        if let Some(seed) = args.get("seed")? {
+            solver = solver.seed(seed);
+        }
+        if weighted {
+            solver = solver.fitness(WeightedFitness::default());
+        }
+        if let Some(pop) = pop_ini {
+            solver = solver.initial_population(pop);
+        }
+        if let Some(path) = args.value("save") {
+            solver = solver.save(path);
+        }
+        eprintln!("seed: {}", solver.get_seed());
+        Ok(Box::new(solver))
+    }
+    else {
+        let mut solver = GeneticSolver::new(max_len, n_gene)
+            .config(config)
+            .initial_solutions(sol_ini)
+            .cancel(cancel.clone());
+        if let Some(seed) = args.get("seed")? {
+            solver = solver.seed(seed);
+        }
+        if let Some(path) = args.value("log") {
+            solver = solver.log(path);
+        }
+        if weighted {
+            solver = solver.fitness(WeightedFitness::default());
+        }
+        if let Some(pop) = pop_ini {
+            solver = solver.initial_population(pop);
+        }
+        if let Some(path) = args.value("save") {
+            solver = solver.save(path);
+        }
+        eprintln!("seed: {}", solver.get_seed());
+        Ok(Box::new(solver))
+    }
+}
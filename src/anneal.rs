@@ -0,0 +1,210 @@
+/*!
+ * 焼きなまし法
+ *
+ * 解の表現と評価は genetic と同じ(u8 の配列を Board::moves() のサイズで剰余をとって使う)。
+ * 適応度関数も genetic のものを使う。
+ * 近傍は 1 点変更・挿入・削除・区間反転。配列長は常に max_len に保ち、
+ * 挿入ではあふれた末尾を捨て、削除では末尾にランダムな値を足す。
+ */
+
+use std::sync::{ Arc };
+use std::time;
+
+use rand::{ prelude::*, prng::{ XorShiftRng } };
+
+use ::{ Cancel, Solver, SolverError };
+use board::{ Board };
+use genetic::{ BoardState, Candidate, Encoding, Fitness, PieceFitness };
+use util;
+
+/// 温度スケジュール。経過時間の割合 p (0..1) に対し温度を決める
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    /// start * (end/start)^p
+    Geometric { start: f64, end: f64 },
+    /// start + (end-start) * p
+    Linear { start: f64, end: f64 },
+}
+
+impl Schedule {
+    fn temperature(&self, p: f64) -> f64 {
+        match *self {
+            Schedule::Geometric { start, end } => start * (end / start).powf(p),
+            Schedule::Linear { start, end }    => start + (end - start) * p,
+        }
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule::Geometric { start: 2000.0, end: 10.0 }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Neighbor {
+    Change,
+    Insert,
+    Delete,
+    Reverse,
+}
+
+pub struct AnnealSolver {
+    board:    Board,
+    max_len:  u32,
+    budget:   time::Duration,
+    schedule: Schedule,
+    seed:     u64,
+    rng:      XorShiftRng,
+    k_ini:    u32,
+    fitness:  Arc<dyn Fitness>,
+    cancel:   Cancel,
+}
+
+impl AnnealSolver {
+    pub fn new(max_len: u32, budget: time::Duration) -> Self {
+        assert!(max_len > 0);
+        let seed = thread_rng().gen();
+        Self {
+            board:    Board::new(0, &[0; 64]),
+            max_len,
+            budget,
+            schedule: Schedule::default(),
+            seed,
+            rng:      util::seeded_rng(seed),
+            k_ini:    0,
+            fitness:  Arc::new(PieceFitness),
+            cancel:   Cancel::new(),
+        }
+    }
+
+    /// 温度スケジュールを指定する
+    pub fn schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// 適応度関数を指定する(デフォルトは PieceFitness)
+    pub fn fitness<F: Fitness + 'static>(mut self, fitness: F) -> Self {
+        self.fitness = Arc::new(fitness);
+        self
+    }
+
+    /// 乱数のシードを指定する(デフォルトはランダム)
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// cancel が要求されたら探索を打ち切り、それまでの解を SolverError として返す
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// 使っているシード
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn neighbor(&mut self, v: &[u8]) -> Vec<u8> {
+        let mut v = v.to_vec();
+        let len = v.len();
+        let op = match self.rng.gen_range(0, 4) {
+            0 => Neighbor::Change,
+            1 => Neighbor::Insert,
+            2 => Neighbor::Delete,
+            _ => Neighbor::Reverse,
+        };
+        match op {
+            Neighbor::Change => {
+                let j = self.rng.gen_range(0, len);
+                v[j] = self.rng.gen();
+            },
+            Neighbor::Insert => {
+                let j = self.rng.gen_range(0, len);
+                v.insert(j, self.rng.gen());
+                v.truncate(len);
+            },
+            Neighbor::Delete => {
+                let j = self.rng.gen_range(0, len);
+                v.remove(j);
+                v.push(self.rng.gen());
+            },
+            Neighbor::Reverse => {
+                let start = self.rng.gen_range(0, len);
+                let end   = self.rng.gen_range(start+1, len+1);
+                v[start..end].reverse();
+            },
+        }
+        v
+    }
+
+    fn random_v(&mut self) -> Vec<u8> {
+        (0..self.max_len)
+            .map(|_| self.rng.gen())
+            .collect()
+    }
+}
+
+impl Solver for AnnealSolver {
+    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
+        // Candidate は解けておらず、打てる手がある局面から始める
+        if board.is_solved() { return Ok(vec![vec![]]); }
+        if board.is_stuck() || board.moves().is_empty() { return Ok(vec![]); }
+
+        self.board = board.clone();
+        self.k_ini = board.counts().iter()
+            .fold(0, |sum,&e| sum + u32::from(e));
+        self.rng = util::seeded_rng(self.seed);
+
+        let mut sols: Vec<Vec<u8>> = vec![];
+        let mut max_len = self.max_len;
+        let v = self.random_v();
+        let mut cur = Candidate::new(&self.board, v, self.k_ini, &*self.fitness, Encoding::Relative);
+        let t_start = time::Instant::now();
+        let budget = util::duration_float(&self.budget);
+        let mut n_iter: u64 = 0;
+        loop {
+            let p = util::duration_float(&t_start.elapsed()) / budget;
+            if p >= 1.0 || max_len == 0 { break; }
+            if self.cancel.is_cancelled() {
+                sols.sort();
+                sols.dedup();
+                return Err(SolverError::cancelled(sols));
+            }
+            let temp = self.schedule.temperature(p);
+
+            let v = self.neighbor(&cur.v);
+            let cand = Candidate::new(&self.board, v, self.k_ini, &*self.fitness, Encoding::Relative);
+            n_iter += 1;
+
+            if cand.state == BoardState::Solved && cand.rotate <= max_len {
+                // 見つけた解より長い手順は以後考えない
+                if cand.rotate < max_len {
+                    eprintln!("Iteration {}: max_len={}", n_iter, cand.rotate);
+                    max_len = cand.rotate;
+                    sols.clear();
+                }
+                sols.push(cand.to_solution());
+            }
+
+            let accept = cand.score >= cur.score || {
+                let delta = cand.score - cur.score;
+                self.rng.gen::<f64>() < (delta / temp).exp()
+            };
+            if accept {
+                cur = cand;
+            }
+            if cur.v.len() > max_len as usize {
+                let v = cur.v[..max_len as usize].to_vec();
+                cur = Candidate::new(&self.board, v, self.k_ini, &*self.fitness, Encoding::Relative);
+            }
+        }
+        eprintln!("Iterations: {}", n_iter);
+
+        sols.sort();
+        sols.dedup();
+        Ok(sols)
+    }
+}
@@ -0,0 +1,316 @@
+/*!
+ * 問題集の一括求解
+ *
+ * 部屋ごとにソルバーを作り直して解き、結果を 1 部屋 1 行の表にまとめる。
+ * 表はテキスト、CSV、JSON のいずれかで出力する。
+ */
+
+use std::fs;
+use std::io::{ self, prelude::* };
+use std::path::{ Path, PathBuf };
+use std::str::{ FromStr };
+use std::time;
+
+use failure;
+
+use ::{ Solver, SolverErrorKind };
+use board::{ Board };
+use cli::{ OptSpec };
+use json;
+use util;
+
+/// "egypt batch" 固有のオプション(ソルバーのオプションは algo::SOLVE_OPTIONS)
+pub const BATCH_OPTIONS: &[OptSpec] = &[
+    OptSpec { name: "format", value: Some("kind"), help: "text|csv|json (default: text)", algos: &[] },
+    OptSpec { name: "output", value: Some("file"), help: "write the report to file instead of stdout", algos: &[] },
+];
+
+/// 部屋を指定しなかったときに解く問題
+pub const PATTERN_DEFAULT: &str = "problem/*.in";
+
+/// 1 部屋の結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    /// 解が見つかった
+    Solved,
+    /// 探索は終わったが解が見つからなかった
+    Unsolved,
+    /// ノード数などの上限に達した
+    Limit,
+    /// Cancel で打ち切られた
+    Cancelled,
+    /// 問題を読めなかったか、ソルバーが作業用ファイルを読み書きできなかった
+    Error(String),
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Status::Solved    => "solved",
+            Status::Unsolved  => "unsolved",
+            Status::Limit     => "limit",
+            Status::Cancelled => "cancelled",
+            Status::Error(_)  => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoomResult {
+    /// 問題ファイル名から拡張子を除いたもの("00-0" など)
+    pub room:       String,
+    pub status:     Status,
+    /// 最短の解の回転数と、その中で最小の歩数
+    pub rotate:     Option<u32>,
+    pub step:       Option<u32>,
+    /// ノード数を数えないソルバーでは None
+    pub nodes:      Option<u64>,
+    /// 秒
+    pub time:       f64,
+    pub n_solution: usize,
+}
+
+impl RoomResult {
+    fn error(room: String, msg: String) -> Self {
+        Self {
+            room,
+            status:     Status::Error(msg),
+            rotate:     None,
+            step:       None,
+            nodes:      None,
+            time:       0.0,
+            n_solution: 0,
+        }
+    }
+}
+
+/// 出力形式
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    Text,
+    Csv,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "csv"  => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            _      => bail!("unknown format: {}", s),
+        }
+    }
+}
+
+// '*' と '?' だけのワイルドカード
+fn wildcard_match(pat: &[u8], s: &[u8]) -> bool {
+    match pat.split_first() {
+        None              => s.is_empty(),
+        Some((b'*', pat)) => (0..=s.len()).any(|i| wildcard_match(pat, &s[i..])),
+        Some((&c, pat))   => match s.split_first() {
+            Some((&d, s)) => (c == b'?' || c == d) && wildcard_match(pat, s),
+            None          => false,
+        },
+    }
+}
+
+/// 問題ファイルの一覧を作る
+///
+/// ファイル名の部分に '*' や '?' を含むものはディレクトリ内の一致するファイルに展開する
+/// (シェルが展開しなかった場合用)。展開結果は名前順。
+pub fn expand_patterns<S: AsRef<str>>(patterns: &[S]) -> Result<Vec<PathBuf>, failure::Error> {
+    let mut res = vec![];
+    for pattern in patterns {
+        let pattern = pattern.as_ref();
+        let path = Path::new(pattern);
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+        if !name.contains(['*', '?']) {
+            res.push(path.to_path_buf());
+            continue;
+        }
+
+        let dir = match path.parent() {
+            Some(dir) if dir != Path::new("") => dir,
+            _                                 => Path::new("."),
+        };
+        let mut paths = vec![];
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let matched = entry.file_name().to_str()
+                .is_some_and(|s| wildcard_match(name.as_bytes(), s.as_bytes()));
+            if matched {
+                paths.push(dir.join(entry.file_name()));
+            }
+        }
+        if paths.is_empty() {
+            bail!("no match: {}", pattern);
+        }
+        paths.sort();
+        res.append(&mut paths);
+    }
+    Ok(res)
+}
+
+/// 部屋 room を解く
+pub fn solve_room(room: &str, board: &Board, solver: &mut dyn Solver) -> RoomResult {
+    let t = time::Instant::now();
+    let r = solver.solve(board);
+    let time = util::duration_float(&t.elapsed());
+
+    let (sols, status) = match r {
+        Ok(sols) => {
+            let status = if sols.is_empty() { Status::Unsolved } else { Status::Solved };
+            (sols, status)
+        },
+        Err(e) => {
+            let status = match *e.kind() {
+                SolverErrorKind::Limit     => Status::Limit,
+                SolverErrorKind::Cancelled => Status::Cancelled,
+                SolverErrorKind::Io(_)     => Status::Error(e.to_string()),
+            };
+            (e.solutions().to_vec(), status)
+        },
+    };
+    let best = util::solutions_with_step(board, &sols).into_iter().next();
+    RoomResult {
+        room:       room.to_owned(),
+        status,
+        rotate:     best.as_ref().map(|(sol, _)| sol.len() as u32),
+        step:       best.as_ref().map(|&(_, step)| step),
+        nodes:      solver.node_count(),
+        time,
+        n_solution: sols.len(),
+    }
+}
+
+/// 問題ファイル path を読んで解く。読めなければ Status::Error
+pub fn solve_file(path: &Path, solver: &mut dyn Solver) -> RoomResult {
+    let room = path.file_stem().map_or_else(|| path.display().to_string(), |s| s.to_string_lossy().into_owned());
+    let board = fs::read_to_string(path)
+        .map_err(failure::Error::from)
+        .and_then(|s| Board::from_str(&s));
+    match board {
+        Ok(board) => solve_room(&room, &board, solver),
+        Err(e)    => RoomResult::error(room, e.to_string()),
+    }
+}
+
+fn opt_str<T: ToString>(x: Option<T>, none: &str) -> String {
+    x.map_or_else(|| none.to_owned(), |x| x.to_string())
+}
+
+fn write_text<W: Write>(w: &mut W, results: &[RoomResult]) -> io::Result<()> {
+    let width = results.iter().map(|res| res.room.len()).max().unwrap_or(0).max(4);
+    writeln!(w, "{:width$}  {:8}  {:>6}  {:>6}  {:>15}  {:>10}  {:>5}",
+             "room", "status", "rotate", "step", "nodes", "time", "sols", width = width)?;
+    for res in results {
+        writeln!(w, "{:width$}  {:8}  {:>6}  {:>6}  {:>15}  {:>10.3}  {:>5}",
+                 res.room, res.status.as_str(),
+                 opt_str(res.rotate, "-"), opt_str(res.step, "-"), opt_str(res.nodes, "-"),
+                 res.time, res.n_solution, width = width)?;
+    }
+    let n_solved = results.iter().filter(|res| res.status == Status::Solved).count();
+    let time: f64 = results.iter().map(|res| res.time).sum();
+    writeln!(w, "solved {}/{}, time {:.3}", n_solved, results.len(), time)
+}
+
+fn write_csv<W: Write>(w: &mut W, results: &[RoomResult]) -> io::Result<()> {
+    writeln!(w, "room,status,rotate,step,nodes,time,solutions")?;
+    for res in results {
+        // 部屋名はファイル名由来なので、カンマや引用符を含むときだけ囲む
+        let room = if res.room.contains([',', '"']) {
+            format!("\"{}\"", res.room.replace('"', "\"\""))
+        }
+        else {
+            res.room.clone()
+        };
+        writeln!(w, "{},{},{},{},{},{:.3},{}",
+                 room, res.status.as_str(),
+                 opt_str(res.rotate, ""), opt_str(res.step, ""), opt_str(res.nodes, ""),
+                 res.time, res.n_solution)?;
+    }
+    Ok(())
+}
+
+fn write_json<W: Write>(w: &mut W, results: &[RoomResult]) -> io::Result<()> {
+    let rooms: Vec<_> = results.iter()
+        .map(|res| {
+            let mut v = json::Value::object()
+                .with("room",      res.room.as_str())
+                .with("status",    res.status.as_str())
+                .with("rotate",    res.rotate)
+                .with("step",      res.step)
+                .with("nodes",     res.nodes)
+                .with("time",      res.time)
+                .with("solutions", res.n_solution);
+            if let Status::Error(ref msg) = res.status {
+                v = v.with("error", msg.as_str());
+            }
+            v
+        })
+        .collect();
+    let n_solved = results.iter().filter(|res| res.status == Status::Solved).count();
+    let v = json::Value::object()
+        .with("rooms",  rooms)
+        .with("solved", n_solved)
+        .with("total",  results.len())
+        .with("time",   results.iter().map(|res| res.time).sum::<f64>());
+    writeln!(w, "{}", v)
+}
+
+/// 結果の表を書き出す
+pub fn write_report<W: Write>(w: &mut W, results: &[RoomResult], format: Format) -> io::Result<()> {
+    match format {
+        Format::Text => write_text(w, results),
+        Format::Csv  => write_csv(w, results),
+        Format::Json => write_json(w, results),
+    }
+}
+
+#[test]
+fn test_batch() {
+    use ::{ Cancel };
+    use bfs_disk::{ DiskBfsSolver };
+    use ida::{ IdaSolver };
+
+    assert!(wildcard_match(b"0?-*.in", b"02-5.in"));
+    assert!(!wildcard_match(b"0?-*.in", b"12-5.in"));
+    assert!(!wildcard_match(b"*.in", b"02-5.out"));
+
+    let paths = expand_patterns(&["problem/0?-0.in"]).unwrap();
+    assert_eq!(10, paths.len());
+    assert_eq!(Path::new("problem/00-0.in"), paths[0]);
+    assert!(expand_patterns(&["problem/*.nothing"]).is_err());
+
+    let mut solver = IdaSolver::new(0, 1_000_000_000).verbose(false);
+    let res = solve_file(Path::new("problem/02-0.in"), &mut solver);
+    assert_eq!(Status::Solved, res.status);
+    assert_eq!(Some(8), res.rotate);
+    assert!(res.nodes.unwrap() > 0);
+    assert!(res.n_solution > 0);
+
+    let res2 = solve_file(Path::new("problem/nothing.in"), &mut solver);
+    assert_eq!("error", res2.status.as_str());
+
+    // 打ち切りや作業用ファイルの失敗は上限と区別する
+    let cancel = Cancel::new();
+    cancel.cancel();
+    let mut solver = IdaSolver::new(0, 1_000_000_000).verbose(false).cancel(cancel);
+    assert_eq!(Status::Cancelled, solve_file(Path::new("problem/02-0.in"), &mut solver).status);
+    let mut solver = DiskBfsSolver::new("/dev/null/egypt", 1000);
+    match solve_file(Path::new("problem/02-0.in"), &mut solver).status {
+        Status::Error(msg) => assert!(msg.starts_with("I/O error: "), "{}", msg),
+        status             => panic!("unexpected status: {:?}", status),
+    }
+
+    let mut csv = vec![];
+    write_report(&mut csv, &[res, res2], Format::Csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(3, lines.len());
+    assert!(lines[1].starts_with("02-0,solved,8,"));
+    assert!(lines[2].starts_with("nothing,error,,,,"));
+}
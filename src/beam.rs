@@ -0,0 +1,169 @@
+/*!
+ * ビームサーチ
+ *
+ * 深さごとに局面を評価値の良い順に width 個だけ残して展開する。
+ * 一度ビームに残した局面は以降の深さでは無視する(同じ局面を回り続けないように)。
+ * 最初に解けた深さで見つかった解を全て返す(最短とは限らない)。
+ * 乱数は使わないので、同じ入力には常に同じ解を返す。
+ *
+ * 幅や max_depth で打ち切って解が見つからなければ SolverError を返す。
+ * 解が無いこと(Ok で空)を返すのは、一度も幅で局面を捨てずにビームが尽きたときだけ。
+ */
+
+use std::collections::{ HashMap, HashSet, hash_map::Entry };
+
+use ::{ Cancel, Solver, SolverError };
+use board::{ Board };
+
+/// 評価値(小さいほど良い)の各項の重み
+///
+/// 評価値 = least * least_to_solve() + pieces * 残りピース数 + rotate * 回転数 + step * 歩数
+///
+/// 同じ深さの局面は回転数が等しいので、rotate は評価値の大きさを変えるだけ。
+/// 歩数を重くすると遠回りの少ない方へ寄りすぎて解けなくなりやすいので、デフォルトは 0。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BeamWeights {
+    pub least:  u32,
+    pub pieces: u32,
+    pub rotate: u32,
+    pub step:   u32,
+}
+
+impl Default for BeamWeights {
+    fn default() -> Self {
+        Self {
+            least:  1,
+            pieces: 2,
+            rotate: 0,
+            step:   0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    board: Board,
+    sol:   Vec<u8>,
+    step:  u32,
+    score: u64,
+}
+
+pub struct BeamSolver {
+    width:     usize,
+    max_depth: u32,
+    weights:   BeamWeights,
+    cancel:    Cancel,
+}
+
+impl BeamSolver {
+    pub fn new(width: usize, max_depth: u32) -> Self {
+        assert!(width > 0);
+        Self {
+            width,
+            max_depth,
+            weights: BeamWeights::default(),
+            cancel:  Cancel::new(),
+        }
+    }
+
+    /// 評価関数の重みを指定する
+    pub fn weights(mut self, weights: BeamWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// cancel が要求されたら探索を打ち切り、それまでの解を SolverError として返す
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    // 解けておらず手詰まりでもない局面を評価する
+    fn evaluate(&self, board: &Board, rotate: u32, step: u32) -> u64 {
+        let w = &self.weights;
+        let pieces: u32 = board.counts().iter()
+            .fold(0, |sum,&e| sum + u32::from(e));
+
+        u64::from(w.least)  * u64::from(board.least_to_solve()) +
+        u64::from(w.pieces) * u64::from(pieces) +
+        u64::from(w.rotate) * u64::from(rotate) +
+        u64::from(w.step)   * u64::from(step)
+    }
+}
+
+impl Solver for BeamSolver {
+    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
+        if board.is_solved() { return Ok(vec![vec![]]); }
+        if board.is_stuck() || board.moves().is_empty() { return Ok(vec![]); }
+
+        let mut visited = HashSet::new();
+        visited.insert(board.region_key());
+        let mut beam = vec![Node {
+            board: board.clone(),
+            sol:   vec![],
+            step:  0,
+            score: 0,
+        }];
+        let mut truncated = false;  // 幅を超えた局面を捨てたか
+
+        for depth in 1..=self.max_depth {
+            if self.cancel.is_cancelled() { return Err(SolverError::cancelled(vec![])); }
+            let mut sols = vec![];
+            // 回転数だけでなく歩数も評価するので、同じ局面は歩数の少ない方を残す
+            let mut index = HashMap::new();
+            let mut children: Vec<Node> = vec![];
+
+            for node in &beam {
+                for to in node.board.moves() {
+                    let step = node.step + node.board.calc_step(node.board.pos, to).unwrap();
+                    let mut board2 = node.board.clone();
+                    board2.move_(to);
+                    let mut sol2 = node.sol.clone();
+                    sol2.push(to);
+
+                    if board2.is_solved() {
+                        sols.push(sol2);
+                        continue;
+                    }
+                    if board2.is_stuck() { continue; }
+                    if board2.moves().is_empty() { continue; }
+                    if visited.contains(&board2.region_key()) { continue; }
+
+                    let score = self.evaluate(&board2, depth, step);
+                    match index.entry(board2.region_key()) {
+                        Entry::Occupied(e) => {
+                            let child: &mut Node = &mut children[*e.get()];
+                            if (step, &sol2) < (child.step, &child.sol) {
+                                *child = Node { board: board2, sol: sol2, step, score };
+                            }
+                        },
+                        Entry::Vacant(e) => {
+                            e.insert(children.len());
+                            children.push(Node { board: board2, sol: sol2, step, score });
+                        },
+                    }
+                }
+            }
+
+            if !sols.is_empty() {
+                sols.sort();
+                sols.dedup();
+                return Ok(sols);
+            }
+            if children.is_empty() {
+                if truncated { break; }
+                // 全ての局面を展開し尽くしたので解は無い
+                return Ok(vec![]);
+            }
+
+            children.sort_by(|a, b| (a.score, &a.sol).cmp(&(b.score, &b.sol)));
+            truncated |= children.len() > self.width;
+            children.truncate(self.width);
+            visited.extend(children.iter().map(|node| node.board.region_key()));
+            eprintln!("Depth {}: best_score={}", depth, children[0].score);
+            beam = children;
+        }
+
+        Err(SolverError::new(vec![]))
+    }
+}
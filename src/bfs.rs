@@ -1,73 +1,196 @@
+use std::cmp;
+use std::collections::{ HashMap, hash_map::Entry };
 use std::io::{ self, prelude::* };
+use std::sync::{ atomic::{ AtomicUsize, Ordering } };
+use std::thread;
 use std::time;
 
-use itertools::{ Itertools };
-
-use ::{ Solver, SolverError };
+use ::{ Cancel, DepthStats, Solver, SolverError, StreamSolver };
 use board::{ Board };
 use util;
 
+// 同じ深さに現れた同一局面は 1 つにまとめ、そこへ至る (親の番号, 手) を全て覚えておく
+// 局面は Board::region_key() で区別する(pos が違っても打てる手が同じなら同一視する)
+// 解は最後にこの親子関係を逆にたどって列挙する
 pub struct BfsSolver {
-    que:            util::Queue<(Board,Vec<u8>)>,
+    boards:         Vec<Board>,
+    parents:        Vec<Vec<Vec<(u32,u8)>>>,  // parents[d][i]: 深さ d+1 の i 番目の局面の親
     max_node_count: u64,
+    n_thread:       usize,
+    total_count:    u64,  // 展開した局面数の合計
+    stats:          Vec<DepthStats>,
+    cancel:         Cancel,
+}
+
+// 1 スレッド分の展開結果
+struct Expansion {
+    solved:   Vec<u32>,
+    children: Vec<(Board,u32,u8)>,
+    overflow: bool,
+}
+
+// n_children は全スレッドで共有する子の数。上限を超えたらどのスレッドも子を作るのをやめる
+// 解けた局面は上限を超えた後も拾うので、見つかる解はスレッドの分け方によらない
+fn expand(boards: &[Board], offset: usize, max_node_count: u64, n_children: &AtomicUsize) -> Expansion {
+    let mut res = Expansion {
+        solved:   vec![],
+        children: vec![],
+        overflow: false,
+    };
+
+    for (i, board) in boards.iter().enumerate() {
+        let i = (offset + i) as u32;
+
+        if board.is_solved() {
+            res.solved.push(i);
+            continue;
+        }
+        if res.overflow { continue; }
+        if board.is_stuck() { continue; }
+        let moves = board.moves();
+        if moves.is_empty() { continue; }
+
+        let n = n_children.fetch_add(moves.len(), Ordering::Relaxed) + moves.len();
+        if n > max_node_count as usize {
+            res.overflow = true;
+            continue;
+        }
+        for to in moves {
+            let mut board2 = board.clone();
+            board2.move_(to);
+            res.children.push((board2, i, to));
+        }
+    }
+
+    res
 }
 
 impl BfsSolver {
     pub fn new(max_node_count: u64) -> Self {
         Self {
-            que: util::Queue::with_capacity(max_node_count as usize),
+            boards:   vec![],
+            parents:  vec![],
             max_node_count,
+            n_thread:    1,
+            total_count: 0,
+            stats:       vec![],
+            cancel:      Cancel::new(),
         }
     }
 
-    fn search_next(&mut self) -> Result<Vec<Vec<u8>>,SolverError> {
-        let mut res = vec![];
+    /// 1 つの深さの展開に使うスレッド数を指定する(デフォルトは 1)
+    pub fn threads(mut self, n_thread: usize) -> Self {
+        assert!(n_thread > 0);
+        self.n_thread = n_thread;
+        self
+    }
 
-        let node_count = self.que.len();
-        for _ in 0..node_count {
-            let (board, sol) = self.que.pop().unwrap();
+    /// cancel が要求されたら次の深さに進む前に探索を打ち切り、それまでの解を SolverError として返す
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
 
-            if board.is_solved() {
-                eprintln!("{}", sol.iter().join(" "));
-                res.push(sol);
-                continue;
-            }
-            if board.is_stuck() { continue; }
-            let moves = board.moves();
-            if moves.is_empty() { continue; }
-
-            for to in moves {
-                let mut board2 = board.clone();
-                board2.move_(to);
-                let mut sol2 = sol.clone();
-                sol2.push(to);
-                self.que.push((board2, sol2));
+    // 解が見つかれば Ok(true) を返す
+    fn search_next(&mut self, depth: u32, f: &mut dyn FnMut(u32, &[u8]) -> bool) -> Result<bool,SolverError> {
+        let max_node_count = self.max_node_count;
+        let n_children = AtomicUsize::new(0);
+        let results = if self.n_thread == 1 {
+            vec![expand(&self.boards, 0, max_node_count, &n_children)]
+        }
+        else {
+            let chunk = cmp::max(1, self.boards.len().div_ceil(self.n_thread));
+            let (boards, n_children) = (&self.boards, &n_children);
+            thread::scope(|s| {
+                let handles: Vec<_> = boards.chunks(chunk)
+                    .enumerate()
+                    .map(|(k, boards)| s.spawn(move || expand(boards, k*chunk, max_node_count, n_children)))
+                    .collect();
+                handles.into_iter()
+                    .map(|h| h.join().expect("worker thread panicked"))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let mut found = false;
+        for i in results.iter().flat_map(|res| res.solved.iter()) {
+            found = true;
+            if !self.emit_solutions(depth as usize, *i, &mut vec![], f) {
+                return Ok(true);
             }
+        }
+        if found { return Ok(true); }
+
+        if results.iter().any(|res| res.overflow) {
+            return Err(SolverError::new(vec![]));
+        }
 
-            if self.que.len() > self.max_node_count as usize {
-                return Err(SolverError::new(res));
+        // 各スレッドの結果をまとめつつ、重複する局面を除く
+        let mut index = HashMap::new();
+        let mut boards  = vec![];
+        let mut parents: Vec<Vec<(u32,u8)>> = vec![];
+        for (board, parent, to) in results.into_iter().flat_map(|res| res.children) {
+            match index.entry(board.region_key()) {
+                Entry::Occupied(e) => {
+                    parents[*e.get() as usize].push((parent, to));
+                },
+                Entry::Vacant(e) => {
+                    boards.push(board);
+                    parents.push(vec![(parent, to)]);
+                    e.insert(boards.len() as u32 - 1);
+                },
+            }
+            if boards.len() > self.max_node_count as usize {
+                return Err(SolverError::new(vec![]));
             }
         }
 
-        Ok(res)
+        self.boards = boards;
+        self.parents.push(parents);
+        Ok(false)
+    }
+
+    // 深さ depth の i 番目の局面に至る手順を全て f に渡す
+    // f が false を返したら false を返す
+    fn emit_solutions(&self, depth: usize, i: u32, rev: &mut Vec<u8>, f: &mut dyn FnMut(u32, &[u8]) -> bool) -> bool {
+        if depth == 0 {
+            let sol: Vec<_> = rev.iter().rev().cloned().collect();
+            return f(sol.len() as u32, &sol);
+        }
+
+        for &(parent, to) in &self.parents[depth-1][i as usize] {
+            rev.push(to);
+            let cont = self.emit_solutions(depth-1, parent, rev, f);
+            rev.pop();
+            if !cont { return false; }
+        }
+        true
     }
 }
 
-impl Solver for BfsSolver {
-    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
-        self.que.clear();
-        self.que.push((board.clone(), vec![]));
+impl StreamSolver for BfsSolver {
+    fn solve_stream(&mut self, board: &Board, f: &mut dyn FnMut(u32, &[u8]) -> bool) -> Result<(),SolverError> {
+        self.boards      = vec![board.clone()];
+        self.parents     = vec![];
+        self.total_count = 0;
+        self.stats       = vec![];
 
         let mut depth = 0;
         loop {
+            // 全ての局面が行き詰まった(解なし)
+            if self.boards.is_empty() { return Ok(()); }
+            if self.cancel.is_cancelled() { return Err(SolverError::cancelled(vec![])); }
+
             eprint!("Depth {}: ", depth);
             io::stderr().flush().unwrap();
             let t = time::Instant::now();
 
-            let node_count = self.que.len();
-            let r = self.search_next();
+            let node_count = self.boards.len();
+            self.total_count += node_count as u64;
+            let r = self.search_next(depth, f);
 
             let mut dur = util::duration_float(&t.elapsed());
+            self.stats.push(DepthStats { depth, nodes: node_count as u64, time: dur });
             if dur < 1e-3 { dur = 1e-3; }
             eprintln!("Nodes={}, Time={:.3}, NPS={:.0}",
                      node_count,
@@ -75,9 +198,9 @@ impl Solver for BfsSolver {
                      node_count as f64 / dur);
 
             match r {
-                Ok(sols) => {
-                    if !sols.is_empty() {
-                        return Ok(sols);
+                Ok(found) => {
+                    if found {
+                        return Ok(());
                     }
                 },
                 Err(e) => { return Err(e); }
@@ -86,3 +209,17 @@ impl Solver for BfsSolver {
         }
     }
 }
+
+impl Solver for BfsSolver {
+    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
+        ::collect_solutions(self, board)
+    }
+
+    fn node_count(&self) -> Option<u64> {
+        Some(self.total_count)
+    }
+
+    fn depth_stats(&self) -> Vec<DepthStats> {
+        self.stats.clone()
+    }
+}
@@ -0,0 +1,364 @@
+/*!
+ * 局面をディスクに書き出しながら進める幅優先探索
+ *
+ * 深さごとの局面を固定長レコードとして、ソート済み・重複なしのファイルに保存する。
+ * レコードは Board::pack() の盤面部分と Board::region() をキーとし、末尾に pos を持つ。
+ * pos だけが違い、打てる手が同じ局面は同一視して 1 つだけ残す。次の深さの局面はメモリに収まる分ずつソートしてファイルに書き出し、
+ * 最後にそれらと過去の全ての深さのファイルをマージすることで重複を除く。
+ *
+ * 各局面はそこへ至る最短手数の深さのファイルにだけ現れる。解が見つかったら、
+ * 深い方から順に「解けた局面へ至る局面」を各深さのファイルから集め、
+ * 初期局面からそれらだけをたどる DFS で全ての最短解を列挙する。
+ */
+
+use std::cmp;
+use std::collections::{ BinaryHeap, HashSet };
+use std::env;
+use std::fs::{ self, File };
+use std::io::{ self, prelude::*, BufReader, BufWriter };
+use std::path::{ Path, PathBuf };
+use std::process;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use std::time;
+
+use ::{ Cancel, DepthStats, Solver, SolverError, StreamSolver };
+use board::{ Board };
+use util;
+
+const KEY_LEN:    usize = Board::PACKED_LEN - 1 + 8;
+const RECORD_LEN: usize = KEY_LEN + 1;
+
+type Key    = [u8; KEY_LEN];
+type Record = [u8; RECORD_LEN];
+
+fn to_record(board: &Board) -> Record {
+    let packed = board.pack();
+    let mut rec = [0; RECORD_LEN];
+    rec[..Board::PACKED_LEN-1].copy_from_slice(&packed[1..]);
+    rec[Board::PACKED_LEN-1..KEY_LEN].copy_from_slice(&board.region().to_be_bytes());
+    rec[KEY_LEN] = packed[0];
+    rec
+}
+
+fn from_record(rec: &Record) -> Board {
+    let mut packed = [0; Board::PACKED_LEN];
+    packed[0] = rec[KEY_LEN];
+    packed[1..].copy_from_slice(&rec[..Board::PACKED_LEN-1]);
+    Board::unpack(&packed)
+}
+
+fn key(rec: &Record) -> Key {
+    let mut res = [0; KEY_LEN];
+    res.copy_from_slice(&rec[..KEY_LEN]);
+    res
+}
+
+fn read_record<R: Read>(r: &mut R) -> io::Result<Option<Record>> {
+    let mut buf = [0; RECORD_LEN];
+    match r.read_exact(&mut buf) {
+        Ok(())                                                => Ok(Some(buf)),
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e)                                                => Err(e),
+    }
+}
+
+// ソート済みのレコードファイルを先頭から読みつつ、昇順に与えられるキーの有無を調べる
+struct SortedReader {
+    r:   BufReader<File>,
+    cur: Option<Record>,
+}
+
+impl SortedReader {
+    fn open(path: &Path) -> io::Result<Self> {
+        let mut r = BufReader::new(File::open(path)?);
+        let cur = read_record(&mut r)?;
+        Ok(Self { r, cur })
+    }
+
+    fn contains(&mut self, k: &Key) -> io::Result<bool> {
+        while let Some(cur) = self.cur {
+            if key(&cur) >= *k { return Ok(key(&cur) == *k); }
+            self.cur = read_record(&mut self.r)?;
+        }
+        Ok(false)
+    }
+}
+
+pub struct DiskBfsSolver {
+    dir_base:       PathBuf,
+    dir:            PathBuf,
+    layer_lens:     Vec<u64>,
+    max_node_count: u64,
+    max_mem_record: usize,
+    total_count:    u64,  // 展開した局面数の合計
+    stats:          Vec<DepthStats>,
+    cancel:         Cancel,
+}
+
+impl DiskBfsSolver {
+    const MAX_MEM_RECORD_DEFAULT: usize = 1 << 22;
+
+    /// dir_base の下に作業用ディレクトリを作り、探索が終わったら削除する
+    pub fn new<P: AsRef<Path>>(dir_base: P, max_node_count: u64) -> Self {
+        Self {
+            dir_base:       dir_base.as_ref().to_path_buf(),
+            dir:            PathBuf::new(),
+            layer_lens:     vec![],
+            max_node_count,
+            max_mem_record: DiskBfsSolver::MAX_MEM_RECORD_DEFAULT,
+            total_count:    0,
+            stats:          vec![],
+            cancel:         Cancel::new(),
+        }
+    }
+
+    /// 一時ファイルを既定の場所(env::temp_dir())に作る
+    pub fn with_temp_dir(max_node_count: u64) -> Self {
+        DiskBfsSolver::new(env::temp_dir(), max_node_count)
+    }
+
+    /// 一度にメモリ上でソートするレコード数を指定する
+    pub fn memory(mut self, max_mem_record: usize) -> Self {
+        assert!(max_mem_record > 0);
+        self.max_mem_record = max_mem_record;
+        self
+    }
+
+    /// cancel が要求されたら次の深さに進む前に探索を打ち切り、それまでの解を SolverError として返す
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    fn layer_path(&self, depth: usize) -> PathBuf {
+        self.dir.join(format!("layer-{}.bin", depth))
+    }
+
+    fn run_path(&self, k: usize) -> PathBuf {
+        self.dir.join(format!("run-{}.bin", k))
+    }
+
+    fn create_dir(&mut self) -> io::Result<()> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let k = COUNTER.fetch_add(1, Ordering::Relaxed);
+        self.dir = self.dir_base.join(format!("egypt-bfs-{}-{}", process::id(), k));
+        fs::create_dir_all(&self.dir)
+    }
+
+    fn remove_dir(&mut self) {
+        if self.dir.as_os_str().is_empty() { return; }
+        let _ = fs::remove_dir_all(&self.dir);
+        self.dir = PathBuf::new();
+    }
+
+    fn write_run(&self, k: usize, buf: &mut Vec<Record>) -> io::Result<()> {
+        buf.sort_unstable();
+        buf.dedup_by(|a, b| key(a) == key(b));
+        let mut w = BufWriter::new(File::create(self.run_path(k))?);
+        for rec in buf.iter() {
+            w.write_all(rec)?;
+        }
+        buf.clear();
+        w.flush()
+    }
+
+    // 深さ depth の局面を展開する
+    // 解けた局面があれば Ok(true) を返す。そうでなければ深さ depth+1 のファイルを作る
+    fn search_next(&mut self, depth: usize) -> io::Result<Result<bool,SolverError>> {
+        let mut r = BufReader::new(File::open(self.layer_path(depth))?);
+        let mut buf = Vec::with_capacity(cmp::min(self.max_mem_record, 1 << 16));
+        let mut n_run = 0;
+        while let Some(rec) = read_record(&mut r)? {
+            let board = from_record(&rec);
+
+            if board.is_solved() { return Ok(Ok(true)); }
+            if board.is_stuck() { continue; }
+            let moves = board.moves();
+            if moves.is_empty() { continue; }
+
+            for to in moves {
+                let mut board2 = board.clone();
+                board2.move_(to);
+                buf.push(to_record(&board2));
+            }
+
+            if buf.len() >= self.max_mem_record {
+                self.write_run(n_run, &mut buf)?;
+                n_run += 1;
+            }
+        }
+        if !buf.is_empty() {
+            self.write_run(n_run, &mut buf)?;
+            n_run += 1;
+        }
+
+        // 各ランをマージしつつ、ラン間の重複と過去の深さに現れた局面を除く
+        let mut runs = vec![];
+        let mut heap = BinaryHeap::new();
+        for k in 0..n_run {
+            let mut r = BufReader::new(File::open(self.run_path(k))?);
+            if let Some(rec) = read_record(&mut r)? {
+                heap.push(cmp::Reverse((rec, k)));
+            }
+            runs.push(r);
+        }
+        let mut prevs = (0..=depth)
+            .map(|d| SortedReader::open(&self.layer_path(d)))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut w = BufWriter::new(File::create(self.layer_path(depth+1))?);
+        let mut last: Option<Key> = None;
+        let mut len = 0;
+        while let Some(cmp::Reverse((rec, k))) = heap.pop() {
+            if let Some(rec) = read_record(&mut runs[k])? {
+                heap.push(cmp::Reverse((rec, k)));
+            }
+
+            let k = key(&rec);
+            if last == Some(k) { continue; }
+            last = Some(k);
+            let mut dup = false;
+            for prev in &mut prevs {
+                if prev.contains(&k)? { dup = true; }
+            }
+            if dup { continue; }
+
+            w.write_all(&rec)?;
+            len += 1;
+            if len > self.max_node_count {
+                return Ok(Err(SolverError::new(vec![])));
+            }
+        }
+        w.flush()?;
+
+        for k in 0..n_run {
+            fs::remove_file(self.run_path(k))?;
+        }
+        self.layer_lens.push(len);
+        Ok(Ok(false))
+    }
+
+    // 深さ depth_goal の解けた局面に至る局面を、深い方から順に各深さのファイルから集める
+    fn collect_useful(&self, depth_goal: usize) -> io::Result<Vec<HashSet<Key>>> {
+        let mut res = vec![HashSet::new(); depth_goal+1];
+
+        let mut r = BufReader::new(File::open(self.layer_path(depth_goal))?);
+        while let Some(rec) = read_record(&mut r)? {
+            if from_record(&rec).is_solved() {
+                res[depth_goal].insert(key(&rec));
+            }
+        }
+
+        for depth in (0..depth_goal).rev() {
+            let mut r = BufReader::new(File::open(self.layer_path(depth))?);
+            while let Some(rec) = read_record(&mut r)? {
+                let board = from_record(&rec);
+                if board.is_stuck() { continue; }
+                let useful = board.moves().into_iter().any(|to| {
+                    let mut board2 = board.clone();
+                    board2.move_(to);
+                    res[depth+1].contains(&key(&to_record(&board2)))
+                });
+                if useful {
+                    res[depth].insert(key(&rec));
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    // collect_useful() で集めた局面だけをたどって解を列挙する
+    // f が false を返したら false を返す
+    fn emit_solutions(useful: &[HashSet<Key>], board: &Board, depth: usize,
+                      sol: &mut Vec<u8>, f: &mut dyn FnMut(u32, &[u8]) -> bool) -> bool {
+        if depth+1 == useful.len() {
+            return f(depth as u32, sol);
+        }
+
+        for to in board.moves() {
+            let mut board2 = board.clone();
+            board2.move_(to);
+            if !useful[depth+1].contains(&key(&to_record(&board2))) { continue; }
+            sol.push(to);
+            let cont = DiskBfsSolver::emit_solutions(useful, &board2, depth+1, sol, f);
+            sol.pop();
+            if !cont { return false; }
+        }
+        true
+    }
+
+    fn solve_io(&mut self, board: &Board, f: &mut dyn FnMut(u32, &[u8]) -> bool) -> io::Result<Result<(),SolverError>> {
+        {
+            let mut w = BufWriter::new(File::create(self.layer_path(0))?);
+            w.write_all(&to_record(board))?;
+            w.flush()?;
+        }
+        self.layer_lens  = vec![1];
+        self.total_count = 0;
+        self.stats       = vec![];
+
+        let mut depth = 0;
+        loop {
+            // 全ての局面が行き詰まった(解なし)
+            if self.layer_lens[depth] == 0 { return Ok(Ok(())); }
+            if self.cancel.is_cancelled() { return Ok(Err(SolverError::cancelled(vec![]))); }
+
+            eprint!("Depth {}: ", depth);
+            io::stderr().flush().unwrap();
+            let t = time::Instant::now();
+
+            let node_count = self.layer_lens[depth];
+            self.total_count += node_count;
+            let r = self.search_next(depth)?;
+
+            let mut dur = util::duration_float(&t.elapsed());
+            self.stats.push(DepthStats { depth: depth as u32, nodes: node_count, time: dur });
+            if dur < 1e-3 { dur = 1e-3; }
+            eprintln!("Nodes={}, Time={:.3}, NPS={:.0}",
+                     node_count,
+                     dur,
+                     node_count as f64 / dur);
+
+            match r {
+                Ok(true) => {
+                    let useful = self.collect_useful(depth)?;
+                    DiskBfsSolver::emit_solutions(&useful, board, 0, &mut vec![], f);
+                    return Ok(Ok(()));
+                },
+                Ok(false) => {},
+                Err(e) => { return Ok(Err(e)); }
+            }
+            depth += 1;
+        }
+    }
+}
+
+impl Drop for DiskBfsSolver {
+    fn drop(&mut self) {
+        self.remove_dir();
+    }
+}
+
+impl StreamSolver for DiskBfsSolver {
+    fn solve_stream(&mut self, board: &Board, f: &mut dyn FnMut(u32, &[u8]) -> bool) -> Result<(),SolverError> {
+        self.remove_dir();
+        let r = self.create_dir().and_then(|_| self.solve_io(board, f));
+        self.remove_dir();
+        r.unwrap_or_else(|e| Err(SolverError::io(&e, vec![])))
+    }
+}
+
+impl Solver for DiskBfsSolver {
+    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
+        ::collect_solutions(self, board)
+    }
+
+    fn node_count(&self) -> Option<u64> {
+        Some(self.total_count)
+    }
+
+    fn depth_stats(&self) -> Vec<DepthStats> {
+        self.stats.clone()
+    }
+}
@@ -0,0 +1,335 @@
+/*!
+ * 上界つき分枝限定法
+ *
+ * まず安価なソルバー(デフォルトはビームサーチ)で解を 1 つ求め、その手数を上界 U とする。
+ * 次に深さ優先で探索し、手数 + least_to_solve() が U を超える枝は刈る。
+ * U より短い解が見つかるたびに U をその手数まで縮めるので、探索が終われば U が最適で、
+ * 見つけた手数 U の解が全ての最適解となる(深さ U を改めて探索し直すことはない)。
+ * 上界の解が得られなかったときは、U を下界 L と仮定して同じ探索を繰り返す。解が無ければ、
+ * 刈った枝の (手数 + least_to_solve()) の最小値が次の L になる(IDA* と同じ)。
+ * 刈った枝が無ければ探索し尽くしたので、解は無い。ノード数の上限は、繰り返した探索の合計に対するもの。
+ *
+ * 上界は探索の途中で縮むたびに、下界は探索を 1 回終えて上がるたびに stderr に出力するので、
+ * 難しい部屋でも打ち切った時点での (L, U) と上界の解が得られる。
+ * 深さ優先の探索の途中では下界は上げない。
+ */
+
+use std::sync::{ Mutex, atomic::{ AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering } };
+use std::thread;
+use std::time;
+
+use ::{ Cancel, DepthStats, Solver, SolverError };
+use beam::{ BeamSolver };
+use board::{ Board };
+use util;
+
+type Solutions = Vec<Vec<u8>>;
+
+fn print_bounds(lower: u32, upper: Option<u32>) {
+    match upper {
+        Some(upper) => eprintln!("Bounds: {} <= rotate <= {}", lower, upper),
+        None        => eprintln!("Bounds: {} <= rotate", lower),
+    }
+}
+
+// 1 回の探索(上界の初期値を固定)の間、全スレッドで共有する状態
+struct Shared<'a> {
+    lower:          u32,
+    upper:          AtomicU32,               // これより長い手順は探索しない
+    solutions:      Mutex<Vec<Vec<u8>>>,     // 見つけた手数 upper の解
+    next_lower:     AtomicU32,               // 刈った枝の (手数 + least_to_solve()) の最小値
+    node_count:     AtomicU64,               // これまでの探索も含めたノード数
+    max_node_count: u64,
+    aborted:        AtomicBool,
+    cancel:         &'a Cancel,
+}
+
+impl<'a> Shared<'a> {
+    fn on_solution(&self, sol: &[u8]) {
+        let mut solutions = self.solutions.lock().unwrap();
+        let len = sol.len() as u32;
+        let upper = self.upper.load(Ordering::Relaxed);
+        if len > upper { return; }
+        if len < upper {
+            solutions.clear();
+            self.upper.store(len, Ordering::Relaxed);
+            print_bounds(self.lower, Some(len));
+        }
+        solutions.push(sol.to_vec());
+    }
+}
+
+// スレッドごとの探索状態
+struct Search<'a, 'b: 'a> {
+    shared:  &'a Shared<'b>,
+    pending: u64,  // まだ shared.node_count に足していないノード数
+}
+
+impl<'a, 'b> Search<'a, 'b> {
+    const FLUSH_INTERVAL: u64 = 1024;
+
+    fn new(shared: &'a Shared<'b>) -> Self {
+        Self {
+            shared,
+            pending: 0,
+        }
+    }
+
+    fn flush(&mut self) {
+        let node_count = self.shared.node_count.fetch_add(self.pending, Ordering::Relaxed) + self.pending;
+        self.pending = 0;
+        if node_count > self.shared.max_node_count || self.shared.cancel.is_cancelled() {
+            self.shared.aborted.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // 局面を訪れ、さらに探索すべきなら打てる手を返す
+    fn visit(&mut self, board: &Board, sol: &[u8]) -> Option<Vec<u8>> {
+        self.pending += 1;
+        if self.pending >= Search::FLUSH_INTERVAL { self.flush(); }
+        if self.shared.aborted.load(Ordering::Relaxed) { return None; }
+
+        if board.is_solved() {
+            self.shared.on_solution(sol);
+            return None;
+        }
+        if board.is_stuck() { return None; }
+        let upper = self.shared.upper.load(Ordering::Relaxed);
+        let f = sol.len() as u32 + board.least_to_solve();
+        if f > upper {
+            self.shared.next_lower.fetch_min(f, Ordering::Relaxed);
+            return None;
+        }
+        let moves = board.moves();
+        if moves.is_empty() { return None; }
+        Some(moves)
+    }
+
+    fn dfs(&mut self, board: &Board, sol: &mut Vec<u8>) {
+        let moves = match self.visit(board, sol) {
+            Some(moves) => moves,
+            None        => return,
+        };
+        for to in moves {
+            let mut board2 = board.clone();
+            board2.move_(to);
+            sol.push(to);
+            self.dfs(&board2, sol);
+            sol.pop();
+        }
+    }
+
+    // 部分木が n_task 個以上になるまで浅い方から展開する
+    fn split(&mut self, board: &Board, n_task: usize) -> Vec<(Board, Vec<u8>)> {
+        let mut tasks = vec![(board.clone(), vec![])];
+        while !tasks.is_empty() && tasks.len() < n_task {
+            let mut tasks_next = vec![];
+            for (board, sol) in tasks {
+                if let Some(moves) = self.visit(&board, &sol) {
+                    for to in moves {
+                        let mut board2 = board.clone();
+                        board2.move_(to);
+                        let mut sol2 = sol.clone();
+                        sol2.push(to);
+                        tasks_next.push((board2, sol2));
+                    }
+                }
+            }
+            tasks = tasks_next;
+        }
+        tasks
+    }
+}
+
+pub struct BnbSolver {
+    upper:          Box<dyn Solver + Send>,
+    max_node_count: u64,
+    n_thread:       usize,
+    bounds:         (u32, Option<u32>),
+    total_count:    u64,  // 分枝限定法で調べたノード数の合計
+    stats:          Vec<DepthStats>,
+    cancel:         Cancel,
+}
+
+impl BnbSolver {
+    const BEAM_WIDTH:        usize = 1000;
+    const BEAM_MAX_DEPTH:    u32   = 100;
+    const N_TASK_PER_THREAD: usize = 16;
+
+    pub fn new(max_node_count: u64) -> Self {
+        Self {
+            upper:       Box::new(BeamSolver::new(BnbSolver::BEAM_WIDTH, BnbSolver::BEAM_MAX_DEPTH)),
+            max_node_count,
+            n_thread:    1,
+            bounds:      (0, None),
+            total_count: 0,
+            stats:       vec![],
+            cancel:      Cancel::new(),
+        }
+    }
+
+    /// 上界を求めるソルバーを指定する(デフォルトは BeamSolver)
+    pub fn upper_bound_solver<S: Solver + Send + 'static>(mut self, solver: S) -> Self {
+        self.upper = Box::new(solver);
+        self
+    }
+
+    /// 分枝限定法のスレッド数を指定する(デフォルトは 1)
+    pub fn threads(mut self, n_thread: usize) -> Self {
+        assert!(n_thread > 0);
+        self.n_thread = n_thread;
+        self
+    }
+
+    /// cancel が要求されたら探索を打ち切り、上界の解を SolverError として返す
+    ///
+    /// 上界を求めるソルバーには別に渡しておく。
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// 直前の solve で得られた (下界, 上界)。上界の解が無ければ None
+    ///
+    /// 最適解が見つかっていれば両者は等しい。
+    pub fn bounds(&self) -> (u32, Option<u32>) {
+        self.bounds
+    }
+
+    fn report(&mut self, lower: u32, upper: Option<u32>) {
+        self.bounds = (lower, upper);
+        print_bounds(lower, upper);
+    }
+
+    // 手数 upper 以下の解を深さ優先で探し、見つけた最短の解と、刈った枝から分かる次の下界を返す
+    // 枝を刈らなかった(探索し尽くした)なら次の下界は None
+    // 上限に達するか打ち切られたら Err で、それまでに見つけた解を返す
+    fn search(&mut self, board: &Board, lower: u32, upper: u32) -> Result<(Solutions,Option<u32>),Solutions> {
+        let shared = Shared {
+            lower,
+            upper:          AtomicU32::new(upper),
+            solutions:      Mutex::new(vec![]),
+            next_lower:     AtomicU32::new(u32::MAX),
+            node_count:     AtomicU64::new(self.total_count),
+            max_node_count: self.max_node_count,
+            aborted:        AtomicBool::new(false),
+            cancel:         &self.cancel,
+        };
+        let t = time::Instant::now();
+
+        if self.n_thread == 1 {
+            let mut search = Search::new(&shared);
+            search.dfs(board, &mut vec![]);
+            search.flush();
+        }
+        else {
+            let tasks = {
+                let mut search = Search::new(&shared);
+                let tasks = search.split(board, BnbSolver::N_TASK_PER_THREAD * self.n_thread);
+                search.flush();
+                tasks
+            };
+            let next = AtomicUsize::new(0);
+            thread::scope(|s| {
+                for _ in 0..self.n_thread {
+                    let (shared, tasks, next) = (&shared, &tasks, &next);
+                    s.spawn(move || {
+                        let mut search = Search::new(shared);
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            if i >= tasks.len() { break; }
+                            let (ref board, ref sol) = tasks[i];
+                            search.dfs(board, &mut sol.clone());
+                        }
+                        search.flush();
+                    });
+                }
+            });
+        }
+
+        let node_count = shared.node_count.load(Ordering::Relaxed) - self.total_count;
+        self.total_count += node_count;
+        self.stats.push(DepthStats {
+            depth: shared.upper.load(Ordering::Relaxed),
+            nodes: node_count,
+            time:  util::duration_float(&t.elapsed()),
+        });
+        let mut sols = shared.solutions.into_inner().unwrap();
+        sols.sort();
+        if shared.aborted.into_inner() { return Err(sols); }
+        let next_lower = shared.next_lower.into_inner();
+        Ok((sols, if next_lower == u32::MAX { None } else { Some(next_lower) }))
+    }
+}
+
+impl Solver for BnbSolver {
+    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
+        self.total_count = 0;
+        self.stats       = vec![];
+        if board.is_solved() {
+            self.bounds = (0, Some(0));
+            return Ok(vec![vec![]]);
+        }
+        if board.is_stuck() || board.moves().is_empty() {
+            self.bounds = (0, None);
+            return Ok(vec![]);
+        }
+
+        // 上界のソルバーは任意なので、解は検証しておく
+        let sols_upper: Vec<Vec<u8>> = match self.upper.solve(board) {
+            Ok(sols)  => sols,
+            Err(e)    => e.solutions().to_vec(),
+        };
+        let sols_upper: Vec<_> = sols_upper.into_iter()
+            .filter(|sol| util::verify_solution(board, sol))
+            .collect();
+        let upper = sols_upper.iter().map(|sol| sol.len() as u32).min();
+        let sols_upper: Vec<_> = sols_upper.into_iter()
+            .filter(|sol| Some(sol.len() as u32) == upper)
+            .collect();
+        let mut lower = board.least_to_solve();
+        self.report(lower, upper);
+
+        // 上界の解があれば 1 回の探索で済む。無ければ上界を下界と仮定し、解が見つかるまで上げていく
+        loop {
+            match self.search(board, lower, upper.unwrap_or(lower)) {
+                Ok((sols, next_lower)) => {
+                    if let Some(len) = sols.first().map(|sol| sol.len() as u32) {
+                        self.report(len, Some(len));
+                        return Ok(sols);
+                    }
+                    // 上界の解があるので、手数 U 以下の解は必ず見つかる
+                    debug_assert!(upper.is_none());
+                    match next_lower {
+                        Some(next_lower) => lower = next_lower,
+                        None             => {
+                            // 探索し尽くしても解が無い
+                            self.bounds = (lower, None);
+                            return Ok(vec![]);
+                        },
+                    }
+                },
+                Err(mut sols) => {
+                    // 上界の解より短い解が見つかっていなければ上界の解も返す
+                    if sols.first().map(|sol| sol.len() as u32) == upper || sols.is_empty() {
+                        sols.extend(sols_upper);
+                        sols.sort();
+                        sols.dedup();
+                    }
+                    self.bounds = (lower, sols.first().map(|sol| sol.len() as u32));
+                    if self.cancel.is_cancelled() { return Err(SolverError::cancelled(sols)); }
+                    return Err(SolverError::new(sols));
+                },
+            }
+            self.report(lower, None);
+        }
+    }
+
+    fn node_count(&self) -> Option<u64> {
+        Some(self.total_count)
+    }
+
+    fn depth_stats(&self) -> Vec<DepthStats> {
+        self.stats.clone()
+    }
+}
@@ -9,7 +9,7 @@ use util;
 
 type BoardArray = generic_array::GenericArray<u8, generic_array::typenum::U64>;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Board {
     pub pos: u8,
     pub v:   BoardArray,
@@ -130,6 +130,35 @@ impl Board {
         }
     }
 
+    // pack() で使うセルの表現(4bit)
+    const PACKED_CELLS: [u8; 16] = [
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9,
+        Board::EMPTY, Board::UP, Board::DOWN, Board::LEFT, Board::RIGHT, Board::WALL,
+    ];
+
+    /// pack() の結果のバイト数
+    pub const PACKED_LEN: usize = 33;
+
+    /// 局面を PACKED_LEN バイトに詰める(先頭 1 バイトが pos、残りは 1 マス 4bit)
+    pub fn pack(&self) -> [u8; Board::PACKED_LEN] {
+        let mut res = [0; Board::PACKED_LEN];
+        res[0] = self.pos;
+        for (i, &cell) in self.v.iter().enumerate() {
+            let c = Board::PACKED_CELLS.iter().position(|&e| e == cell).expect("unexpected cell") as u8;
+            res[1 + i/2] |= c << (4 * (i%2));
+        }
+        res
+    }
+
+    /// pack() の逆変換
+    pub fn unpack(buf: &[u8]) -> Self {
+        debug_assert_eq!(buf.len(), Board::PACKED_LEN);
+        let v: Vec<_> = (0..64)
+            .map(|i| Board::PACKED_CELLS[((buf[1 + i/2] >> (4 * (i%2))) & 0xF) as usize])
+            .collect();
+        Board::new(buf[0], &v)
+    }
+
     pub fn idx2xy(pos: u8) -> (u8, u8) {
         (pos%8, pos/8)
     }
@@ -151,6 +180,24 @@ impl Board {
     }
 
     pub fn moves(&self) -> Vec<u8> {
+        self.explore().0
+    }
+
+    /// プレイヤーが歩いて行けるマス(踏める矢印を含む)のビットマスク
+    ///
+    /// 盤面とこれが同じなら moves() も同じなので、回転数だけを考えるなら
+    /// pos の違いは区別しなくてよい(歩数は後から calc_step() で求める)。
+    pub fn region(&self) -> u64 {
+        self.explore().1
+    }
+
+    /// 回転数だけを考えるときに局面を区別するキー(盤面と region())
+    pub fn region_key(&self) -> (BoardArray, u64) {
+        (self.v, self.region())
+    }
+
+    // pos から歩いて踏める矢印と、歩いて行けるマスのビットマスクを求める
+    fn explore(&self) -> (Vec<u8>, u64) {
         fn is_direction(cell: u8) -> bool {
             cell == Board::UP ||
             cell == Board::DOWN ||
@@ -161,12 +208,12 @@ impl Board {
         let mut res = vec![];
 
         let mut que     = util::Queue::<u8>::new();
-        let mut visited = [false; 64];
+        let mut visited = 0u64;
         // 始点を踏むには一度出てから戻らないといけないことに注意
         for &to in Board::adjacent(self.pos) {
             if self.v[to as usize] == Board::WALL { continue; }
             que.push(to);
-            visited[to as usize] = true;
+            visited |= 1 << to;
         }
 
         while !que.is_empty() {
@@ -178,14 +225,14 @@ impl Board {
             }
 
             for &to in Board::adjacent(i) {
-                if visited[to as usize] { continue; }
+                if visited & (1 << to) != 0 { continue; }
                 if self.v[to as usize] == Board::WALL { continue; }
                 que.push(to);
-                visited[to as usize] = true;
+                visited |= 1 << to;
             }
         }
 
-        res
+        (res, visited)
     }
 
     pub fn move_(&mut self, pos: u8) {
@@ -333,6 +380,51 @@ impl Board {
         }
     }
 
+    // pos の矢印が回転させる列: (縦方向か, 列または行の番号)。矢印でなければ None
+    fn rotation_line(&self, pos: u8) -> Option<(bool, u8)> {
+        let (x, y) = Board::idx2xy(pos);
+        match self.v[pos as usize] {
+            Board::UP   | Board::DOWN  => Some((true, x)),
+            Board::LEFT | Board::RIGHT => Some((false, y)),
+            _                          => None,
+        }
+    }
+
+    /// 手 a, b がこの局面から順序によらず同じ結果になるか
+    ///
+    /// 同じ向きの別の列を回す 2 手について、どちらの順序でも打つことができ、
+    /// 途中の局面が解けても詰んでもおらず、最後の局面の盤面と打てる手が一致すれば真。
+    /// (プレイヤーの位置は違うが、以降の探索には影響しない)
+    pub fn commutes(&self, a: u8, b: u8) -> bool {
+        let (vert_a, line_a) = match self.rotation_line(a) { Some(e) => e, None => return false };
+        let (vert_b, line_b) = match self.rotation_line(b) { Some(e) => e, None => return false };
+        if vert_a != vert_b || line_a == line_b { return false; }
+
+        let moves = self.moves();
+        if !moves.contains(&a) || !moves.contains(&b) { return false; }
+
+        let mut board_a = self.clone();
+        board_a.move_(a);
+        let mut board_b = self.clone();
+        board_b.move_(b);
+        for board in &[&board_a, &board_b] {
+            if board.is_solved() || board.is_stuck() { return false; }
+        }
+        if !board_a.moves().contains(&b) || !board_b.moves().contains(&a) { return false; }
+
+        let mut board_ab = board_a;
+        board_ab.move_(b);
+        let mut board_ba = board_b;
+        board_ba.move_(a);
+        if board_ab.v != board_ba.v { return false; }
+
+        let mut moves_ab = board_ab.moves();
+        moves_ab.sort_unstable();
+        let mut moves_ba = board_ba.moves();
+        moves_ba.sort_unstable();
+        moves_ab == moves_ba
+    }
+
     pub fn counts(&self) -> [u8; Board::N_KIND] {
         let mut res = [0; Board::N_KIND];
         self.v.iter()
@@ -347,7 +439,7 @@ impl Board {
 
     pub fn is_stuck(&self) -> bool {
         //if self.moves().is_empty() { return true; }
-        if self.counts().iter().any(|&n| n == 1) { return true; }
+        if self.counts().contains(&1) { return true; }
         false
     }
 
@@ -385,11 +477,9 @@ impl Board {
         dist[dst as usize]
     }
 
-    // 解くまでに最低限必要な手数を求める
-    // 盤面はまだ解かれておらず、解が存在するものとする(先に is_stuck() チェックが必要)
-    // 盤面は正しいものとする(消えるはずのピースが消えていないとかはナシ)
-    // ピース間の距離で判断(15パズルのマンハッタン距離枝刈りみたいな感じ)
-    pub fn least_to_solve(&self) -> u32 {
+    // 種類ごとに、最も近いピース同士の距離を求める(2 個未満の種類は None)
+    // "1..<...1" みたいな状態を考慮して、距離は最低でも 2 とする
+    pub fn pair_distances(&self) -> [Option<u32>; Board::N_KIND] {
         let mut v = [None; Board::N_KIND];
         for i in 0..64 {
             let pi = self.v[i];
@@ -397,7 +487,6 @@ impl Board {
             for j in i+1..64 {
                 let pj = self.v[j];
                 if pi == pj {
-                    // "1..<...1" みたいな状態を考慮
                     let d_new = cmp::max(2, Board::DIST[i][j]);
                     v[pi as usize] = match v[pi as usize] {
                         Some(d) => Some(cmp::min(d, d_new)),
@@ -406,7 +495,15 @@ impl Board {
                 }
             }
         }
-        v.iter().flat_map(|&e| e).max().unwrap() - 1
+        v
+    }
+
+    // 解くまでに最低限必要な手数を求める
+    // 盤面はまだ解かれておらず、解が存在するものとする(先に is_stuck() チェックが必要)
+    // 盤面は正しいものとする(消えるはずのピースが消えていないとかはナシ)
+    // ピース間の距離で判断(15パズルのマンハッタン距離枝刈りみたいな感じ)
+    pub fn least_to_solve(&self) -> u32 {
+        self.pair_distances().iter().flat_map(|&e| e).max().unwrap() - 1
     }
 }
 
@@ -423,7 +520,7 @@ impl fmt::Display for Board {
                     Board::LEFT  => '<',
                     Board::RIGHT => '>',
                     Board::WALL  => '#',
-                    n @ 0 ... 9  => char::from(b'0' + n),
+                    n @ 0 ..= 9  => char::from(b'0' + n),
                     x            => panic!("unexpected cell: {}", x),
                 })?;
             }
@@ -439,26 +536,32 @@ impl FromStr for Board {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut lines = s.lines();
-        let mut it = lines.next().unwrap()
+        let pos: Vec<u8> = lines.next().unwrap_or("")
             .split_whitespace()
-            .map(|token| token.parse::<u8>().unwrap());
-        let pos_x = it.next().unwrap();
-        let pos_y = it.next().unwrap();
+            .map(|token| token.parse())
+            .collect::<Result<_,_>>()?;
+        let (pos_x, pos_y) = match pos[..] {
+            [x, y] if x < 8 && y < 8 => (x, y),
+            _ => bail!("board: invalid position"),
+        };
 
         let v: Vec<_> = lines
             .take(8)
             .flat_map(|line| line.chars())
-            .map(|c| match c {
+            .map(|c| Ok(match c {
                 '.'         => Board::EMPTY,
                 '^'         => Board::UP,
                 'v'         => Board::DOWN,
                 '<'         => Board::LEFT,
                 '>'         => Board::RIGHT,
                 '#'         => Board::WALL,
-                '0' ... '9' => c as u8 - b'0',
-                _           => panic!("unexpected char: {}", c),
-            })
-            .collect();
+                '0' ..= '9' => c as u8 - b'0',
+                _           => bail!("board: unexpected char: {}", c),
+            }))
+            .collect::<Result<_, failure::Error>>()?;
+        if v.len() != 64 {
+            bail!("board: expected 8x8 cells, got {}", v.len());
+        }
 
         Ok(Self {
             pos: Board::xy2idx(pos_x, pos_y),
@@ -492,7 +595,30 @@ fn test_board() {
     assert!(!board.is_solved());
     assert!(!board.is_stuck());
     assert_eq!(Some(7), board.calc_step(17,45));
+    assert_eq!([Some(2),Some(2),Some(5),Some(2),Some(2),None,None,None,None], board.pair_distances());
     assert_eq!(4, board.least_to_solve());
+    assert_eq!(board, Board::unpack(&board.pack()));
+    {
+        // 入力で使える全てのセルを詰められる
+        let board: Board = board_str.replace("#010", "#919").parse().unwrap();
+        assert_eq!(board, Board::unpack(&board.pack()));
+    }
+
+    // 壊れた入力はエラー
+    assert!("".parse::<Board>().is_err());
+    assert!("8 1\n#######4".parse::<Board>().is_err());
+    assert!(board_str.replace("#010", "#01x").parse::<Board>().is_err());
+    assert!(board_str.replace("#......v\n", "").parse::<Board>().is_err());
+    assert!(!board.commutes(18, 42));
+    {
+        // 同じ領域内で pos だけ違う局面は同じキーになる
+        let mut board2 = board.clone();
+        board2.pos = Board::xy2idx(4,4);
+        assert_eq!(board.region_key(), board2.region_key());
+        board2.pos = Board::xy2idx(7,0);
+        assert_eq!(0, board2.region());
+        assert_ne!(board.region_key(), board2.region_key());
+    }
 
     {
         let mut board = board.clone();
@@ -0,0 +1,329 @@
+/*!
+ * 解のキャッシュ
+ *
+ * 局面ごとの最短解をディレクトリ内の 1 ファイルに保存し、同じ局面を解くときはそれを返す。
+ * ファイル名は局面(pos と盤面)の FNV-1a ハッシュを 16 進 16 桁にしたもの。
+ * 中身はテキスト形式:
+ *
+ * ```text
+ * solver ida
+ * limits max-nodes=100000000
+ * solution 3 17 20 ...
+ * board
+ * <Board の Display 形式>
+ * ```
+ *
+ * solver と limits は解を求めたときのアルゴリズムと制限。制限をつけて解くときは、
+ * その制限で解いても同じ解が得られる記録だけを使う(Entry::satisfies())。
+ * ハッシュが衝突しても board を比べるので、別の局面の解を返すことはない。
+ * 読めないファイルや、解になっていない手順を含むファイルは壊れているものとして無視する。
+ */
+
+use std::env;
+use std::fs;
+use std::io::{ self, prelude::* };
+use std::path::{ Path, PathBuf };
+use std::process;
+
+use failure;
+use itertools::{ Itertools };
+
+use ::{ DepthStats, Solver, SolverError };
+use board::{ Board };
+use util;
+
+/// 局面のハッシュ(FNV-1a, 64bit)
+pub fn board_hash(board: &Board) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME:  u64 = 0x0000_0100_0000_01b3;
+    board.v.iter()
+        .chain(Some(&board.pos))
+        .fold(OFFSET, |h, &b| (h ^ u64::from(b)).wrapping_mul(PRIME))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub board:     Board,
+    pub solver:    String,
+    pub limits:    String,
+    pub solutions: Vec<Vec<u8>>,
+}
+
+impl Entry {
+    fn load(path: &Path) -> Option<Self> {
+        let s = fs::read_to_string(path).ok()?;
+        Entry::parse(&s).ok()
+            .filter(|entry| entry.solutions.iter().all(|sol| util::verify_solution(&entry.board, sol)))
+    }
+
+    fn parse(s: &str) -> Result<Self, failure::Error> {
+        let mut solver    = String::new();
+        let mut limits    = String::new();
+        let mut solutions = vec![];
+        let mut lines = s.lines();
+        while let Some(line) = lines.next() {
+            let (key, rest) = match line.find(' ') {
+                Some(i) => (&line[..i], &line[i+1..]),
+                None    => (line, ""),
+            };
+            match key {
+                "solver"   => { solver = rest.trim().to_owned(); },
+                "limits"   => { limits = rest.trim().to_owned(); },
+                "solution" => { solutions.push(util::parse_solution(rest)?); },
+                "board"    => {
+                    let board = lines.join("\n").parse()?;
+                    return Ok(Self {
+                        board,
+                        solver,
+                        limits,
+                        solutions,
+                    });
+                },
+                ""         => {},
+                _          => bail!("cache: unexpected line: {}", line),
+            }
+        }
+        bail!("cache: board missing")
+    }
+
+    /// limits(記録の limits と同じ形式)の制限で解いても、この記録の解が得られるか
+    ///
+    /// max-depth は解の回転数が収まればよい。それ以外(max-nodes)は、記録したときにも
+    /// 同じ制限があり、その値が limits の値以下でなければならない。
+    pub fn satisfies(&self, limits: &str) -> bool {
+        fn parse(limits: &str) -> Vec<(&str, u64)> {
+            limits.split_whitespace()
+                .filter_map(|kv| {
+                    let mut it = kv.splitn(2, '=');
+                    Some((it.next()?, it.next()?.parse().ok()?))
+                })
+                .collect()
+        }
+        let stored = parse(&self.limits);
+        parse(limits).into_iter().all(|(name, value)| match name {
+            "max-depth" => self.solutions.iter().all(|sol| sol.len() as u64 <= value),
+            _           => stored.iter().any(|&(name2, value2)| name2 == name && value2 <= value),
+        })
+    }
+
+    /// テキスト形式で書き出す。正しい解には回転数と歩数をコメントで添える
+    pub fn write<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "solver {}", self.solver)?;
+        writeln!(w, "limits {}", self.limits)?;
+        for sol in &self.solutions {
+            if util::verify_solution(&self.board, sol) {
+                let step = util::step_of_solution(&self.board, sol);
+                writeln!(w, "solution {} # rotate={} step={}", sol.iter().join(" "), sol.len(), step)?;
+            }
+            else {
+                writeln!(w, "solution {}", sol.iter().join(" "))?;
+            }
+        }
+        writeln!(w, "board")?;
+        write!(w, "{}", self.board)
+    }
+}
+
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+        }
+    }
+
+    /// 既定のキャッシュディレクトリ
+    ///
+    /// $EGYPT_CACHE_DIR、$XDG_CACHE_HOME/egypt、$HOME/.cache/egypt の順に探す。
+    pub fn default_dir() -> Option<PathBuf> {
+        let var = |name| env::var_os(name).filter(|s| !s.is_empty()).map(PathBuf::from);
+        var("EGYPT_CACHE_DIR")
+            .or_else(|| var("XDG_CACHE_HOME").map(|dir| dir.join("egypt")))
+            .or_else(|| var("HOME").map(|dir| dir.join(".cache").join("egypt")))
+    }
+
+    pub fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+
+    fn path(&self, board: &Board) -> PathBuf {
+        self.dir.join(format!("{:016x}.txt", board_hash(board)))
+    }
+
+    /// board の記録。無いか、読めないか壊れていれば None
+    pub fn get(&self, board: &Board) -> Option<Entry> {
+        Entry::load(&self.path(board)).filter(|entry| entry.board == *board)
+    }
+
+    /// 記録を保存する(同じ局面の記録は置き換える)
+    ///
+    /// 一時ファイルに書いてから置き換えるので、読み手が書きかけの内容を見ることはない。
+    pub fn put(&self, entry: &Entry) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(&entry.board);
+        let path_tmp = path.with_extension(format!("tmp{}", process::id()));
+        {
+            let mut w = io::BufWriter::new(fs::File::create(&path_tmp)?);
+            entry.write(&mut w)?;
+            w.flush()?;
+        }
+        fs::rename(&path_tmp, path)
+    }
+
+    /// board の記録を消す。消したら true
+    pub fn remove(&self, board: &Board) -> io::Result<bool> {
+        if self.get(board).is_none() { return Ok(false); }
+        fs::remove_file(self.path(board))?;
+        Ok(true)
+    }
+
+    /// 全ての記録を消し、消した数を返す
+    pub fn clear(&self) -> io::Result<usize> {
+        let mut n = 0;
+        for path in self.files()? {
+            fs::remove_file(path)?;
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    /// 全ての記録(ファイル名順)。読めないファイルや壊れたファイルは無視する
+    pub fn entries(&self) -> io::Result<Vec<Entry>> {
+        Ok(self.files()?
+            .into_iter()
+            .filter_map(|path| Entry::load(&path))
+            .collect())
+    }
+
+    fn files(&self) -> io::Result<Vec<PathBuf>> {
+        let rd = match fs::read_dir(&self.dir) {
+            Ok(rd) => rd,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        let mut res = vec![];
+        for entry in rd {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "txt") {
+                res.push(path);
+            }
+        }
+        res.sort();
+        Ok(res)
+    }
+}
+
+/// 解く前にキャッシュを調べるソルバー
+///
+/// limits を満たす記録があればそれを返す。無ければ inner で解き、store が真で解が見つかれば記録する。
+/// 最短解を全て返すとは限らないソルバー(近似解法など)では store を偽にする。
+pub struct CachedSolver {
+    inner:  Box<dyn Solver + Send>,
+    cache:  Cache,
+    solver: String,
+    limits: String,
+    store:  bool,
+    hit:    Option<Entry>,
+}
+
+impl CachedSolver {
+    pub fn new(inner: Box<dyn Solver + Send>, cache: Cache, solver: &str, limits: &str, store: bool) -> Self {
+        Self {
+            inner,
+            cache,
+            solver: solver.to_owned(),
+            limits: limits.to_owned(),
+            store,
+            hit:    None,
+        }
+    }
+}
+
+impl Solver for CachedSolver {
+    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
+        self.hit = self.cache.get(board).filter(|entry| entry.satisfies(&self.limits));
+        if let Some(ref entry) = self.hit {
+            eprintln!("cache hit: {:016x} (solver {}, limits {})", board_hash(board), entry.solver, entry.limits);
+            return Ok(entry.solutions.clone());
+        }
+
+        let sols = self.inner.solve(board)?;
+        if self.store && !sols.is_empty() {
+            let entry = Entry {
+                board:     board.clone(),
+                solver:    self.solver.clone(),
+                limits:    self.limits.clone(),
+                solutions: sols.clone(),
+            };
+            if let Err(e) = self.cache.put(&entry) {
+                eprintln!("cannot write cache: {}", e);
+            }
+        }
+        Ok(sols)
+    }
+
+    fn node_count(&self) -> Option<u64> {
+        if self.hit.is_some() { Some(0) } else { self.inner.node_count() }
+    }
+
+    fn depth_stats(&self) -> Vec<DepthStats> {
+        if self.hit.is_some() { vec![] } else { self.inner.depth_stats() }
+    }
+
+    fn cache_hit(&self) -> Option<&Entry> {
+        self.hit.as_ref()
+    }
+}
+
+#[test]
+fn test_cache() {
+    use std::str::{ FromStr };
+    use ida::{ IdaSolver };
+
+    let board = Board::from_str(&fs::read_to_string("problem/00-2.in").unwrap()).unwrap();
+    let sols = IdaSolver::new(0, 1_000_000_000).verbose(false).solve(&board).unwrap();
+    let mut board2 = board.clone();
+    board2.pos = board2.moves()[0];
+    assert_ne!(board_hash(&board), board_hash(&board2));
+
+    let cache = Cache::new(env::temp_dir().join(format!("egypt-test-cache-{}", process::id())));
+    assert_eq!(None, cache.get(&board));
+    let entry = Entry {
+        board:     board.clone(),
+        solver:    "ida".to_owned(),
+        limits:    "max-nodes=100".to_owned(),
+        solutions: sols,
+    };
+    cache.put(&entry).unwrap();
+    assert_eq!(Some(entry.clone()), cache.get(&board));
+    assert_eq!(None, cache.get(&board2));
+    assert_eq!(vec![entry.clone()], cache.entries().unwrap());
+
+    assert!(!cache.remove(&board2).unwrap());
+    assert!(cache.remove(&board).unwrap());
+    assert_eq!(None, cache.get(&board));
+
+    // 制限をつけて解いても得られる記録か
+    assert!(entry.satisfies("default"));
+    assert!(entry.satisfies("max-depth=2"));
+    assert!(!entry.satisfies("max-depth=1"));
+    assert!(entry.satisfies("max-nodes=100 max-depth=2"));
+    assert!(!entry.satisfies("max-nodes=99"));
+    assert!(!Entry { limits: "default".to_owned(), ..entry.clone() }.satisfies("max-nodes=100"));
+
+    // 解になっていない記録は使わない
+    cache.put(&Entry { solutions: vec![vec![0]], ..entry }).unwrap();
+    assert_eq!(None, cache.get(&board));
+
+    // 手が数でないファイルも壊れているものとして無視する
+    let text = fs::read_to_string(cache.path(&board)).unwrap();
+    assert!(text.contains("\nsolution 0\n"));
+    fs::write(cache.path(&board), text.replacen("solution 0", "solution 0 x", 1)).unwrap();
+    assert_eq!(None, cache.get(&board));
+    assert!(cache.entries().unwrap().is_empty());
+    assert_eq!(1, cache.clear().unwrap());
+    fs::remove_dir(cache.dir()).unwrap();
+}
@@ -0,0 +1,131 @@
+/*!
+ * 反復深化系ソルバーの途中経過
+ *
+ * テキスト形式で保存する:
+ *
+ * ```text
+ * max_depth 12
+ * node_count 123456789
+ * progress 0 3 1
+ * solution 9 10 11 ...
+ * board
+ * <Board の Display 形式>
+ * ```
+ *
+ * progress は初期局面から子の番号(Board::moves() の添字)をたどった手順で、
+ * max_depth の反復においてこれより前の部分木は探索済みであることを表す。
+ * solution はその範囲で見つかった解。
+ */
+
+use std::fs;
+use std::io::{ self, prelude::* };
+use std::path::{ Path };
+
+use failure;
+use itertools::{ Itertools };
+
+use board::{ Board };
+use util;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub board:      Board,
+    pub max_depth:  u32,
+    pub node_count: u64,
+    pub progress:   Vec<u32>,
+    pub solutions:  Vec<Vec<u8>>,
+}
+
+impl Checkpoint {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, failure::Error> {
+        let s = fs::read_to_string(path)?;
+
+        let mut max_depth  = None;
+        let mut node_count = 0;
+        let mut progress   = vec![];
+        let mut solutions  = vec![];
+        let mut lines = s.lines();
+        while let Some(line) = lines.next() {
+            let (key, rest) = match line.find(' ') {
+                Some(i) => (&line[..i], &line[i+1..]),
+                None    => (line, ""),
+            };
+            match key {
+                "max_depth"  => { max_depth = Some(rest.trim().parse()?); },
+                "node_count" => { node_count = rest.trim().parse()?; },
+                "progress"   => {
+                    progress = rest.split_whitespace()
+                        .map(|w| w.parse())
+                        .collect::<Result<_,_>>()?;
+                },
+                "solution"   => { solutions.push(util::parse_solution(rest)?); },
+                "board"      => {
+                    let board = lines.join("\n").parse()?;
+                    let max_depth = max_depth.ok_or_else(|| format_err!("checkpoint: max_depth missing"))?;
+                    return Ok(Self {
+                        board,
+                        max_depth,
+                        node_count,
+                        progress,
+                        solutions,
+                    });
+                },
+                ""           => {},
+                _            => bail!("checkpoint: unexpected line: {}", line),
+            }
+        }
+        bail!("checkpoint: board missing")
+    }
+
+    /// 一時ファイルに書いてから置き換えるので、途中で落ちても以前の内容は残る
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut path_tmp = path.as_os_str().to_owned();
+        path_tmp.push(".tmp");
+
+        {
+            let mut w = io::BufWriter::new(fs::File::create(&path_tmp)?);
+            writeln!(w, "max_depth {}", self.max_depth)?;
+            writeln!(w, "node_count {}", self.node_count)?;
+            writeln!(w, "progress {}", self.progress.iter().join(" "))?;
+            for sol in &self.solutions {
+                writeln!(w, "solution {}", sol.iter().join(" "))?;
+            }
+            writeln!(w, "board")?;
+            write!(w, "{}", self.board)?;
+            w.flush()?;
+        }
+        fs::rename(&path_tmp, path)
+    }
+}
+
+#[test]
+fn test_checkpoint() {
+    use std::env;
+    use std::process;
+
+    let board: Board = "\
+1 1
+#######4
+#010...#
+#.<1.^2#
+#.2.1..#
+3......#
+#.v..>.3
+#......v
+#######4
+".parse().unwrap();
+    let ckpt = Checkpoint {
+        board,
+        max_depth:  12,
+        node_count: 12345,
+        progress:   vec![0, 3, 1],
+        solutions:  vec![vec![18, 21], vec![42, 45, 55]],
+    };
+
+    let path = env::temp_dir().join(format!("egypt-test-checkpoint-{}", process::id()));
+    ckpt.save(&path).unwrap();
+    let ckpt2 = Checkpoint::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(ckpt, ckpt2);
+}
@@ -0,0 +1,187 @@
+/*!
+ * コマンドライン引数の解析
+ *
+ * オプションは OptSpec の表で定義する。"--name value" と "--name=value" の両方を受け付け、
+ * "--" 以降は全て位置引数とみなす。"-h" と "--help" は常に使える。
+ */
+
+use std::collections::{ HashMap, HashSet };
+use std::fmt;
+use std::fs;
+use std::io::{ self, prelude::* };
+use std::str::{ FromStr };
+
+use failure;
+
+/// オプションの定義
+#[derive(Debug, Clone, Copy)]
+pub struct OptSpec {
+    /// "--" を除いた名前
+    pub name:  &'static str,
+    /// 値の説明。None なら値をとらないフラグ
+    pub value: Option<&'static str>,
+    pub help:  &'static str,
+    /// このオプションを使うアルゴリズム(空なら全て)
+    pub algos: &'static [&'static str],
+}
+
+/// 解析済みの引数
+#[derive(Debug, Clone, Default)]
+pub struct Args {
+    pub positional: Vec<String>,
+    values:         HashMap<String, String>,
+    flags:          HashSet<String>,
+}
+
+impl Args {
+    pub fn parse<S: AsRef<str>>(args: &[S], spec: &[OptSpec]) -> Result<Self, failure::Error> {
+        let mut res = Args::default();
+        let mut it = args.iter().map(|s| s.as_ref());
+        while let Some(arg) = it.next() {
+            if arg == "--" {
+                res.positional.extend(it.by_ref().map(String::from));
+                break;
+            }
+            if arg == "-h" || arg == "--help" {
+                res.flags.insert("help".to_owned());
+                continue;
+            }
+            if !arg.starts_with("--") || arg == "-" {
+                res.positional.push(arg.to_owned());
+                continue;
+            }
+
+            let (name, value) = match arg.find('=') {
+                Some(i) => (&arg[2..i], Some(&arg[i+1..])),
+                None    => (&arg[2..], None),
+            };
+            let opt = spec.iter()
+                .find(|opt| opt.name == name)
+                .ok_or_else(|| format_err!("unknown option: --{}", name))?;
+            match (opt.value, value) {
+                (Some(_), Some(value)) => { res.values.insert(name.to_owned(), value.to_owned()); },
+                (Some(_), None)        => {
+                    let value = it.next().ok_or_else(|| format_err!("option --{} needs a value", name))?;
+                    res.values.insert(name.to_owned(), value.to_owned());
+                },
+                (None, Some(_))        => bail!("option --{} takes no value", name),
+                (None, None)           => { res.flags.insert(name.to_owned()); },
+            }
+        }
+        Ok(res)
+    }
+
+    /// 値をとらないフラグが指定されたか
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    /// 文字列の値
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(|s| s.as_str())
+    }
+
+    /// 値を T として解析する。指定されていなければ None
+    pub fn get<T>(&self, name: &str) -> Result<Option<T>, failure::Error>
+        where T: FromStr, T::Err: fmt::Display
+    {
+        match self.value(name) {
+            Some(s) => s.parse()
+                .map(Some)
+                .map_err(|e| format_err!("option --{}: {}: {}", name, s, e)),
+            None    => Ok(None),
+        }
+    }
+
+    /// 値を T として解析する。指定されていなければ default
+    pub fn get_or<T>(&self, name: &str, default: T) -> Result<T, failure::Error>
+        where T: FromStr, T::Err: fmt::Display
+    {
+        Ok(self.get(name)?.unwrap_or(default))
+    }
+
+    /// カンマ区切りの値を n 個の T として解析する
+    pub fn get_list<T>(&self, name: &str, n: usize) -> Result<Option<Vec<T>>, failure::Error>
+        where T: FromStr, T::Err: fmt::Display
+    {
+        let s = match self.value(name) {
+            Some(s) => s,
+            None    => return Ok(None),
+        };
+        let v: Vec<T> = s.split(',')
+            .map(|e| e.trim().parse())
+            .collect::<Result<_,_>>()
+            .map_err(|e| format_err!("option --{}: {}: {}", name, s, e))?;
+        if v.len() != n {
+            bail!("option --{}: expected {} comma-separated values: {}", name, n, s);
+        }
+        Ok(Some(v))
+    }
+
+    /// 指定されたオプションのうち、アルゴリズム algo で使わないもの
+    pub fn unused(&self, spec: &[OptSpec], algo: &str) -> Vec<&'static str> {
+        spec.iter()
+            .filter(|opt| !opt.algos.is_empty() && !opt.algos.contains(&algo))
+            .filter(|opt| self.values.contains_key(opt.name) || self.flags.contains(opt.name))
+            .map(|opt| opt.name)
+            .collect()
+    }
+}
+
+/// オプションの一覧を usage 用に整形する
+pub fn format_options(spec: &[OptSpec]) -> String {
+    let heads: Vec<_> = spec.iter()
+        .map(|opt| match opt.value {
+            Some(value) => format!("--{} <{}>", opt.name, value),
+            None        => format!("--{}", opt.name),
+        })
+        .collect();
+    let width = heads.iter().map(|head| head.len()).max().unwrap_or(0);
+
+    let mut res = String::new();
+    for (opt, head) in spec.iter().zip(&heads) {
+        res += &format!("    {:width$}  {}", head, opt.help, width = width);
+        if !opt.algos.is_empty() {
+            res += &format!(" [{}]", opt.algos.join(","));
+        }
+        res += "\n";
+    }
+    res
+}
+
+/// path のファイルを読む。None または "-" なら stdin から読む
+pub fn read_input(path: Option<&str>) -> io::Result<String> {
+    match path {
+        Some(path) if path != "-" => fs::read_to_string(path),
+        _ => {
+            let mut s = String::new();
+            io::stdin().read_to_string(&mut s)?;
+            Ok(s)
+        },
+    }
+}
+
+#[test]
+fn test_args() {
+    const SPEC: &[OptSpec] = &[
+        OptSpec { name: "width",  value: Some("n"),   help: "", algos: &["beam"] },
+        OptSpec { name: "reduce", value: None,        help: "", algos: &["ida"] },
+        OptSpec { name: "temp",   value: Some("s,e"), help: "", algos: &[] },
+    ];
+
+    let args = Args::parse(&["a", "--width", "10", "--reduce", "--temp=2,1.5", "-", "--", "--b"], SPEC).unwrap();
+    assert_eq!(vec!["a", "-", "--b"], args.positional);
+    assert_eq!(Some(10), args.get::<usize>("width").unwrap());
+    assert_eq!(3, args.get_or("missing", 3).unwrap());
+    assert!(args.flag("reduce"));
+    assert!(!args.flag("help"));
+    assert_eq!(Some(vec![2.0, 1.5]), args.get_list::<f64>("temp", 2).unwrap());
+    assert!(args.get_list::<f64>("temp", 3).is_err());
+    assert_eq!(vec!["width"], args.unused(SPEC, "ida"));
+
+    assert!(Args::parse(&["--unknown"], SPEC).is_err());
+    assert!(Args::parse(&["--width"], SPEC).is_err());
+    assert!(Args::parse(&["--reduce=1"], SPEC).is_err());
+    assert!(Args::parse(&["-h"], SPEC).unwrap().flag("help"));
+    assert!(Args::parse(&["--width", "x"], SPEC).unwrap().get::<usize>("width").is_err());
+}
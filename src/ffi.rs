@@ -0,0 +1,423 @@
+/*!
+ * C から使うための API
+ *
+ * cdylib としてビルドし、include/egypt.h と共に使う。関数名は全て egypt_ で始まる。
+ *
+ * 局面(EgyptBoard)と解いた結果(EgyptResult)は不透明なポインタで渡し、
+ * それぞれ egypt_board_free(), egypt_result_free() で解放する。
+ * 手はセルの番号(8*y + x)で表す。
+ *
+ * 失敗した関数は NULL や負の値を返し、その理由は egypt_last_error() で得られる
+ * (呼び出したスレッドで最後に起きたエラー)。内部で panic しても C 側へは巻き戻さず、
+ * 同じように失敗として返す。
+ *
+ * 解のキャッシュは、egypt_solve() のオプションに --cache か --cache-dir があるときだけ使う。
+ */
+
+use std::cell::{ RefCell };
+use std::ffi::{ CStr, CString };
+use std::os::raw::{ c_char, c_int };
+use std::panic;
+use std::ptr;
+use std::slice;
+use std::str::{ FromStr };
+
+use failure;
+
+use algo;
+use board::{ Board };
+use cli::{ Args };
+use util;
+use ::{ SolverErrorKind };
+
+pub const EGYPT_SOLVED:   c_int = 0;
+pub const EGYPT_UNSOLVED: c_int = 1;
+pub const EGYPT_LIMIT:    c_int = 2;
+
+/// 局面
+pub struct EgyptBoard {
+    board: Board,
+}
+
+/// solve の結果。解は回転数、歩数の少ない順
+pub struct EgyptResult {
+    status:    c_int,
+    solutions: Vec<(Vec<u8>,u32)>,
+    nodes:     Option<u64>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(msg: &str) {
+    let msg = CString::new(msg.replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(msg));
+}
+
+// f を実行し、panic したら理由をエラーとして残して err を返す
+// (panic を C の呼び出し元へ巻き戻すと、呼び出し元のプロセスごと落ちる)
+fn guard<T, F: FnOnce() -> T>(err: T, f: F) -> T {
+    match panic::catch_unwind(panic::AssertUnwindSafe(f)) {
+        Ok(x)        => x,
+        Err(payload) => {
+            set_error(&format!("internal error: {}", util::panic_message(&*payload)));
+            err
+        },
+    }
+}
+
+unsafe fn to_str<'a>(s: *const c_char) -> Result<&'a str, failure::Error> {
+    if s.is_null() { bail!("null string"); }
+    Ok(CStr::from_ptr(s).to_str()?)
+}
+
+/// このスレッドで最後に起きたエラーの説明。無ければ NULL
+///
+/// 次にこのスレッドで egypt_ の関数を呼ぶまで有効。
+#[no_mangle]
+pub extern "C" fn egypt_last_error() -> *const c_char {
+    guard(ptr::null(), || {
+        LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+    })
+}
+
+/// テキスト形式の局面を読む。失敗したら NULL
+///
+/// # Safety
+/// text は NUL 終端の文字列。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_board_parse(text: *const c_char) -> *mut EgyptBoard {
+    guard(ptr::null_mut(), || {
+        match to_str(text).and_then(Board::from_str) {
+            Ok(board) => Box::into_raw(Box::new(EgyptBoard { board })),
+            Err(e)    => { set_error(&e.to_string()); ptr::null_mut() },
+        }
+    })
+}
+
+/// # Safety
+/// board は egypt_board_parse() などで得たもの。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_board_clone(board: *const EgyptBoard) -> *mut EgyptBoard {
+    guard(ptr::null_mut(), || {
+        Box::into_raw(Box::new(EgyptBoard { board: (*board).board.clone() }))
+    })
+}
+
+/// # Safety
+/// board は egypt_board_parse() などで得たもので、まだ解放していないもの(NULL なら何もしない)。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_board_free(board: *mut EgyptBoard) {
+    guard((), || {
+        if !board.is_null() {
+            drop(Box::from_raw(board));
+        }
+    })
+}
+
+/// テキスト形式の局面。egypt_string_free() で解放する
+///
+/// # Safety
+/// board は有効な局面。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_board_to_string(board: *const EgyptBoard) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        CString::new((*board).board.to_string()).unwrap().into_raw()
+    })
+}
+
+/// # Safety
+/// s は egypt_board_to_string() で得たもの(NULL なら何もしない)。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_string_free(s: *mut c_char) {
+    guard((), || {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    })
+}
+
+/// プレイヤーの位置(セルの番号)
+///
+/// # Safety
+/// board は有効な局面。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_board_pos(board: *const EgyptBoard) -> u8 {
+    guard(u8::MAX, || {
+        (*board).board.pos
+    })
+}
+
+/// 打てる手を最大 cap 個 out に書き、手の総数を返す
+///
+/// 総数が cap より多ければ先頭の cap 個だけ書く。out が NULL なら総数だけ返す。
+///
+/// # Safety
+/// board は有効な局面で、out は NULL か cap 個の書き込める領域。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_board_moves(board: *const EgyptBoard, out: *mut u8, cap: usize) -> usize {
+    guard(0, || {
+        let moves = (*board).board.moves();
+        if !out.is_null() {
+            let n = moves.len().min(cap);
+            slice::from_raw_parts_mut(out, n).copy_from_slice(&moves[..n]);
+        }
+        moves.len()
+    })
+}
+
+/// 手を打つ。打てない手なら局面は変えずに -1、打てたらその手の歩数を返す
+///
+/// # Safety
+/// board は有効な局面。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_board_apply(board: *mut EgyptBoard, pos: u8) -> c_int {
+    guard(-1, || {
+        let board = &mut (*board).board;
+        if !board.moves().contains(&pos) {
+            set_error(&format!("illegal move: {}", pos));
+            return -1;
+        }
+        let step = board.calc_step(board.pos, pos).unwrap();
+        board.move_(pos);
+        step as c_int
+    })
+}
+
+/// # Safety
+/// board は有効な局面。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_board_is_solved(board: *const EgyptBoard) -> c_int {
+    guard(-1, || {
+        (*board).board.is_solved() as c_int
+    })
+}
+
+/// 解けておらず、これ以上解ける見込みが無ければ 1(打てる手が無い場合も含む)
+///
+/// # Safety
+/// board は有効な局面。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_board_is_stuck(board: *const EgyptBoard) -> c_int {
+    guard(-1, || {
+        let board = &(*board).board;
+        (!board.is_solved() && (board.is_stuck() || board.moves().is_empty())) as c_int
+    })
+}
+
+/// 解くのに必要な回転数の下界。解けていれば 0、行き詰まっていれば -1
+///
+/// # Safety
+/// board は有効な局面。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_board_least_to_solve(board: *const EgyptBoard) -> c_int {
+    guard(-1, || {
+        let board = &(*board).board;
+        if board.is_solved() {
+            0
+        } else if board.is_stuck() || board.moves().is_empty() {
+            -1
+        } else {
+            board.least_to_solve() as c_int
+        }
+    })
+}
+
+/// "egypt solve" と同じオプション(空白区切り、NULL なら既定)で解く。オプションが誤っていれば NULL
+///
+/// 例: "--algo ida --max-nodes 100000000"。解き終わるまで戻らない。
+/// 解のキャッシュは --cache か --cache-dir を与えたときだけ使う。
+/// 作業用ファイルの読み書きに失敗したときも NULL。
+///
+/// # Safety
+/// board は有効な局面で、options は NULL か NUL 終端の文字列。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_solve(board: *const EgyptBoard, options: *const c_char) -> *mut EgyptResult {
+    guard(ptr::null_mut(), || {
+        let options = if options.is_null() { Ok("") } else { to_str(options) };
+        let r = options.and_then(|options| {
+            let argv: Vec<_> = options.split_whitespace().collect();
+            let args = Args::parse(&argv, algo::SOLVE_OPTIONS)?;
+            if !args.positional.is_empty() { bail!("unexpected argument: {}", args.positional[0]); }
+            if args.value("checkpoint").is_some() || args.value("resume").is_some() {
+                bail!("--checkpoint and --resume cannot be used here");
+            }
+            algo::build_solver(&args)
+        });
+        let mut solver = match r {
+            Ok(solver) => solver,
+            Err(e)     => { set_error(&e.to_string()); return ptr::null_mut(); },
+        };
+
+        let board = &(*board).board;
+        let (sols, status) = match solver.solve(board) {
+            Ok(sols) => {
+                let status = if sols.is_empty() { EGYPT_UNSOLVED } else { EGYPT_SOLVED };
+                (sols, status)
+            },
+            Err(e) => {
+                if let SolverErrorKind::Io(_) = *e.kind() {
+                    set_error(&e.to_string());
+                    return ptr::null_mut();
+                }
+                (e.solutions().to_vec(), EGYPT_LIMIT)
+            },
+        };
+        Box::into_raw(Box::new(EgyptResult {
+            status,
+            solutions: util::solutions_with_step(board, &sols),
+            nodes:     solver.node_count(),
+        }))
+    })
+}
+
+/// # Safety
+/// result は egypt_solve() で得たもので、まだ解放していないもの(NULL なら何もしない)。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_result_free(result: *mut EgyptResult) {
+    guard((), || {
+        if !result.is_null() {
+            drop(Box::from_raw(result));
+        }
+    })
+}
+
+/// EGYPT_SOLVED, EGYPT_UNSOLVED, EGYPT_LIMIT のいずれか
+///
+/// EGYPT_LIMIT(ノード数などの上限に達した)でも、それまでに見つけた解は取り出せる。
+///
+/// # Safety
+/// result は有効な結果。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_result_status(result: *const EgyptResult) -> c_int {
+    guard(-1, || {
+        (*result).status
+    })
+}
+
+/// 解の数
+///
+/// # Safety
+/// result は有効な結果。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_result_count(result: *const EgyptResult) -> usize {
+    guard(0, || {
+        (*result).solutions.len()
+    })
+}
+
+/// i 番目の解の手を最大 cap 個 out に書き、解の手数(回転数)を返す。i が範囲外なら 0
+///
+/// out が NULL なら手数だけ返す。
+///
+/// # Safety
+/// result は有効な結果で、out は NULL か cap 個の書き込める領域。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_result_solution(result: *const EgyptResult, i: usize, out: *mut u8, cap: usize) -> usize {
+    guard(0, || {
+        let result = &*result;
+        let sol = match result.solutions.get(i) {
+            Some((sol, _)) => sol,
+            None           => return 0,
+        };
+        if !out.is_null() {
+            let n = sol.len().min(cap);
+            slice::from_raw_parts_mut(out, n).copy_from_slice(&sol[..n]);
+        }
+        sol.len()
+    })
+}
+
+/// i 番目の解の歩数。i が範囲外なら 0
+///
+/// # Safety
+/// result は有効な結果。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_result_step(result: *const EgyptResult, i: usize) -> u32 {
+    guard(0, || {
+        let result = &*result;
+        result.solutions.get(i).map_or(0, |&(_, step)| step)
+    })
+}
+
+/// 調べたノード数。数えないソルバーなら -1
+///
+/// # Safety
+/// result は有効な結果。
+#[no_mangle]
+pub unsafe extern "C" fn egypt_result_nodes(result: *const EgyptResult) -> i64 {
+    guard(-1, || {
+        (*result).nodes.map_or(-1, |n| n as i64)
+    })
+}
+
+#[test]
+fn test_ffi() {
+    let text = CString::new(::std::fs::read_to_string("problem/00-2.in").unwrap()).unwrap();
+    unsafe {
+        let board = egypt_board_parse(text.as_ptr());
+        assert!(!board.is_null());
+        let n = egypt_board_moves(board, ptr::null_mut(), 0);
+        let mut moves = vec![0; n];
+        assert_eq!(n, egypt_board_moves(board, moves.as_mut_ptr(), n));
+        assert_eq!((*board).board.moves(), moves);
+
+        let options = CString::new("--algo bfs").unwrap();
+        let result = egypt_solve(board, options.as_ptr());
+        assert_eq!(EGYPT_SOLVED, egypt_result_status(result));
+        assert_eq!(2, egypt_result_count(result));
+        let mut sol = [0; 2];
+        assert_eq!(2, egypt_result_solution(result, 0, sol.as_mut_ptr(), 2));
+        assert_eq!(3, egypt_result_step(result, 0));
+        egypt_result_free(result);
+
+        let board2 = egypt_board_clone(board);
+        assert_eq!(-1, egypt_board_apply(board2, 0));
+        assert!(egypt_board_apply(board2, sol[0]) >= 0);
+        assert!(egypt_board_apply(board2, sol[1]) >= 0);
+        assert_eq!(1, egypt_board_is_solved(board2));
+        assert_eq!(0, egypt_board_is_stuck(board2));
+        assert_eq!(0, egypt_board_least_to_solve(board2));
+        assert_eq!(0, egypt_board_is_solved(board));
+        egypt_board_free(board2);
+
+        let options = CString::new("--algo nothing").unwrap();
+        assert!(egypt_solve(board, options.as_ptr()).is_null());
+        assert!(!egypt_last_error().is_null());
+        egypt_board_free(board);
+
+        let text = CString::new("1 1\n###").unwrap();
+        assert!(egypt_board_parse(text.as_ptr()).is_null());
+
+        // 手詰まりの局面では下界は -1
+        let text = CString::new("1 1\n#######4\n#.10...#\n#.<1.^2#\n#.2.1..#\n3......#\n#.v..>.3\n#......v\n#######4").unwrap();
+        let board = egypt_board_parse(text.as_ptr());
+        assert!(!board.is_null());
+        assert_eq!(1, egypt_board_is_stuck(board));
+        assert_eq!(-1, egypt_board_least_to_solve(board));
+        egypt_board_free(board);
+
+        // 解けている局面では空の解が 1 つ
+        let text = CString::new(format!("0 0{}", "\n........".repeat(8))).unwrap();
+        let board = egypt_board_parse(text.as_ptr());
+        assert!(!board.is_null());
+        assert_eq!(0, egypt_board_least_to_solve(board));
+        let options = CString::new("--algo genetic --max-len 5").unwrap();
+        let result = egypt_solve(board, options.as_ptr());
+        assert_eq!(EGYPT_SOLVED, egypt_result_status(result));
+        assert_eq!(1, egypt_result_count(result));
+        assert_eq!(0, egypt_result_solution(result, 0, ptr::null_mut(), 0));
+        egypt_result_free(result);
+        egypt_board_free(board);
+    }
+}
+
+#[test]
+fn test_guard() {
+    // panic は C 側へ巻き戻さず、エラーの値と理由にする
+    assert_eq!(-1, guard(-1, || -> c_int { panic!("boom") }));
+    let e = unsafe { CStr::from_ptr(egypt_last_error()) }.to_str().unwrap();
+    assert_eq!("internal error: boom", e);
+    assert_eq!(3, guard(-1, || 3));
+}
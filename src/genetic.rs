@@ -1,50 +1,224 @@
-/**
+/*!
  * 遺伝的アルゴリズムらしきもの
  *
  * 個体は u8 の配列で表現する。
- * 実際に Board へ適用するときは Board::moves() のサイズで剰余をとる。
+ * 実際に Board へ適用するときは Encoding に従って手に変換する。
+ * デフォルトは Board::moves() のサイズで剰余をとる(Encoding::Relative)。
  */
 
 use std::cmp;
-//use std::io::{ self, prelude::* };
+use std::collections::{ HashMap };
+use std::fs::{ File };
+use std::io::{ self, prelude::*, BufWriter };
+use std::path::{ PathBuf };
+use std::sync::{ Arc };
 //use std::time;
 
 //use itertools::{ Itertools };
-use rand::{ prelude::*, distributions::{ Weighted, WeightedChoice } };
+use rand::{ prelude::*, prng::{ XorShiftRng }, distributions::{ Weighted, WeightedChoice } };
 
-use ::{ Solver, SolverError };
+use ::{ Cancel, Solver, SolverError, StreamSolver };
 use board::{ Board };
-//use util;
+use ida::{ IdaSolver };
+use population::{ Population };
+use util;
 
+/// 遺伝子から手への変換方法
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum BoardState {
+pub enum Encoding {
+    /// Board::moves() の添字(サイズで剰余をとる)
+    ///
+    /// 前の手が変わると同じ遺伝子でも別の手になる。
+    Relative,
+    /// 回転させる矢印の位置(64 で剰余をとる)
+    ///
+    /// その位置が合法手でなければ、最も近い合法手に修正する。
+    Absolute,
+}
+
+impl Encoding {
+    // 遺伝子 e を局面 board での手に変換する。board は合法手を持つこと
+    fn decode(self, board: &Board, e: u8) -> u8 {
+        let moves = board.moves();
+        match self {
+            Encoding::Relative => moves[e as usize % moves.len()],
+            Encoding::Absolute => {
+                let pos = e % 64;
+                let (x, y) = Board::idx2xy(pos);
+                *moves.iter()
+                    .min_by_key(|&&to| {
+                        let (tx, ty) = Board::idx2xy(to);
+                        let d = (i32::from(x) - i32::from(tx)).abs() + (i32::from(y) - i32::from(ty)).abs();
+                        (d, to)
+                    })
+                    .unwrap()
+            },
+        }
+    }
+
+    // 局面 board での手 to を遺伝子に変換する
+    fn encode(self, board: &Board, to: u8) -> u8 {
+        match self {
+            Encoding::Relative => {
+                board.moves().iter().position(|&e| e == to).expect("invalid solution") as u8
+            },
+            Encoding::Absolute => to,
+        }
+    }
+}
+
+/// 個体を適用し終えた局面の状態
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BoardState {
     Solved,
     Stuck,
     NoMove,
     Playing,
 }
 
+/// 適応度関数に渡す個体の情報
 #[derive(Debug)]
-struct Candidate {
-    v:      Vec<u8>,
-    state:  BoardState,
-    rotate: u32,
-    step:   u32,
-    score:  u32,
+pub struct Evaluation<'a> {
+    /// 個体を適用し終えた局面
+    pub board:  &'a Board,
+    pub state:  BoardState,
+    pub rotate: u32,
+    /// calc_step() の合計
+    pub step:   u32,
+    /// 初期局面のピース数
+    pub k_ini:  u32,
+}
+
+/// 適応度関数(大きいほど良い)
+///
+/// 負の値を返してもよい。ルーレット選択では最小値が 0 になるようにずらして使う。
+/// NaN は f64::total_cmp() の順序で比べる(探索は続くが、選択はうまく働かない)。
+pub trait Fitness: Send + Sync {
+    fn evaluate(&self, e: &Evaluation) -> f64;
+}
+
+/// 残りピース数の 2 乗で評価する(従来の評価関数)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PieceFitness;
+
+impl Fitness for PieceFitness {
+    fn evaluate(&self, e: &Evaluation) -> f64 {
+        let rotate = f64::from(e.rotate);
+        if e.state == BoardState::Solved {
+            return 100000.0 - 100.0*rotate - f64::from(e.step);
+        }
+
+        let k: u32 = e.board.counts().iter()
+            .fold(0, |sum,&n| sum + u32::from(n));
+        let ratio = (f64::from(k) / f64::from(e.k_ini)).powi(2);
+
+        match e.state {
+            BoardState::Solved  => unreachable!(),
+            BoardState::Stuck   => 1000.0 + 150.0*rotate - 800.0*ratio,
+            BoardState::NoMove  => 1000.0 + 150.0*rotate - 800.0*ratio,
+            BoardState::Playing => 10000.0 + 150.0*rotate - 8000.0*ratio,
+        }
+    }
+}
+
+/// 各項の重み付き和で評価する
+///
+/// 解けた個体:     solved - rotate * 回転数 - step * 歩数
+///
+/// 解けていない個体: alive * 回転数 - pieces * 残りピース数 / 初期ピース数
+///                   - least * least_to_solve() - pairing * 種類ごとのペア間距離の合計
+///                   - walk * 歩数 - stuck * 1 個だけ残った種類の数 - no_move (動けなくなった場合)
+///
+/// 手詰まりの局面では least_to_solve() は意味を持たないので使わない。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightedFitness {
+    pub solved:  f64,
+    pub rotate:  f64,
+    pub step:    f64,
+    pub alive:   f64,
+    pub pieces:  f64,
+    pub least:   f64,
+    pub pairing: f64,
+    pub walk:    f64,
+    pub stuck:   f64,
+    pub no_move: f64,
+}
+
+impl Default for WeightedFitness {
+    fn default() -> Self {
+        Self {
+            solved:  100000.0,
+            rotate:  100.0,
+            step:    1.0,
+            alive:   150.0,
+            pieces:  8000.0,
+            least:   500.0,
+            pairing: 50.0,
+            walk:    1.0,
+            stuck:   3000.0,
+            no_move: 9000.0,
+        }
+    }
+}
+
+impl Fitness for WeightedFitness {
+    fn evaluate(&self, e: &Evaluation) -> f64 {
+        let rotate = f64::from(e.rotate);
+        if e.state == BoardState::Solved {
+            return self.solved - self.rotate*rotate - self.step*f64::from(e.step);
+        }
+
+        let counts = e.board.counts();
+        let k: u32 = counts.iter()
+            .fold(0, |sum,&n| sum + u32::from(n));
+        let n_single = counts.iter()
+            .filter(|&&n| n == 1)
+            .count();
+        let pairing: u32 = e.board.pair_distances().iter()
+            .flat_map(|&d| d)
+            .sum();
+
+        let mut score = self.alive*rotate
+            - self.pieces * f64::from(k) / f64::from(e.k_ini)
+            - self.pairing * f64::from(pairing)
+            - self.walk * f64::from(e.step);
+        match e.state {
+            BoardState::Solved  => unreachable!(),
+            BoardState::Stuck   => { score -= self.stuck * n_single as f64; },
+            BoardState::NoMove  => { score -= self.no_move; },
+            BoardState::Playing => { score -= self.least * f64::from(e.board.least_to_solve()); },
+        }
+        score
+    }
+}
+
+// 個体とその評価結果(anneal からも使う)
+#[derive(Debug)]
+pub(crate) struct Candidate {
+    pub(crate) v:      Vec<u8>,
+    pub(crate) state:  BoardState,
+    pub(crate) rotate: u32,
+    #[allow(dead_code)]
+    pub(crate) step:   u32,
+    pub(crate) score:  f64,
+    // v を適用し終えた局面
+    pub(crate) board:  Board,
+    // v を変換した手順
+    sol:               Vec<u8>,
 }
 
 impl Candidate {
-    fn new(board: &Board, v: Vec<u8>, k_ini: u32) -> Self {
+    pub(crate) fn new(board: &Board, v: Vec<u8>, k_ini: u32, fitness: &dyn Fitness, encoding: Encoding) -> Self {
         let mut board  = board.clone();
         let mut state  = BoardState::Playing;
         let mut rotate = 0;
         let mut step   = 0;
+        let mut sol    = vec![];
 
         // 初期局面はまだ解かれておらず、手詰まりでもないと仮定している
         for &e in &v {
-            let moves = board.moves();
-            let idx = e as usize % moves.len();
-            let dst = moves[idx];
+            let dst = encoding.decode(&board, e);
+            sol.push(dst);
             rotate += 1;
             step   += board.calc_step(board.pos, dst).unwrap();
             board.move_(dst);
@@ -64,7 +238,13 @@ impl Candidate {
             }
         }
 
-        let score = Candidate::evaluate(&board, state, rotate, step, k_ini);
+        let score = fitness.evaluate(&Evaluation {
+            board: &board,
+            state,
+            rotate,
+            step,
+            k_ini,
+        });
 
         Self {
             v,
@@ -72,38 +252,8 @@ impl Candidate {
             rotate,
             step,
             score,
-        }
-    }
-
-    fn evaluate(board: &Board, state: BoardState, rotate: u32, step: u32, k_ini: u32) -> u32 {
-        if state == BoardState::Solved {
-            return 100000 - 100*rotate - step;
-        }
-
-        /*
-        let cnt = board.counts();
-        let pena1: u32 = cnt.iter()
-            .fold(0, |sum,&e| sum + u32::from(e));
-        let pena2 = cnt.iter()
-            .filter(|&e| e % 2 != 0)
-            .count() as u32;
-        let pena = 10*pena1 + 3*pena2;
-        */
-        let cnt = board.counts();
-        let k: u32 = cnt.iter()
-            .fold(0, |sum,&e| sum + u32::from(e));
-
-        match state {
-            BoardState::Solved => unreachable!(),
-            BoardState::Stuck => {
-                1000 + 150*rotate - 800*k.pow(2) / k_ini.pow(2)
-            },
-            BoardState::NoMove => {
-                1000 + 150*rotate - 800*k.pow(2) / k_ini.pow(2)
-            },
-            BoardState::Playing => {
-                10000 + 150*rotate - 8000*k.pow(2) / k_ini.pow(2)
-            },
+            board,
+            sol,
         }
     }
 
@@ -111,20 +261,80 @@ impl Candidate {
         self.v[..len as usize].to_vec()
     }
 
-    fn to_solution(&self, board: &Board) -> Vec<u8> {
-        let mut res = vec![];
+    pub(crate) fn to_solution(&self) -> Vec<u8> {
+        self.sol.clone()
+    }
+}
 
-        let mut board = board.clone();
-        for i in 0..self.rotate {
-            let e = self.v[i as usize];
-            let moves = board.moves();
-            let idx = e as usize % moves.len();
-            let pos = moves[idx];
-            res.push(pos);
-            board.move_(pos);
-        }
+/// 交叉の方法
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Crossover {
+    OnePoint,
+    TwoPoint,
+    Uniform,
+}
 
-        res
+/// 突然変異の方法(いずれも配列長は変えない)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Mutation {
+    /// 1 箇所をランダムな値にする
+    Point,
+    /// 2 箇所を入れ替える
+    Swap,
+    /// 1 箇所にランダムな値を挿入し、末尾を捨てる
+    Insert,
+    /// 1 箇所を削除し、末尾にランダムな値を足す
+    Delete,
+}
+
+/// 親の選択方法
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Selection {
+    /// スコアに比例した確率で選ぶ
+    Roulette,
+    /// ランダムに選んだ n 個のうち最良のものを選ぶ
+    Tournament(usize),
+}
+
+/// GeneticSolver のパラメータ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneticConfig {
+    /// 個体数
+    pub n_cand:           usize,
+    /// そのまま次の世代に残す上位の個体数
+    pub n_elite:          usize,
+    /// 各操作(複製・交叉・突然変異)を選ぶ重み
+    pub weight_copy:      u32,
+    pub weight_crossover: u32,
+    pub weight_mutate:    u32,
+    pub crossover:        Crossover,
+    pub mutation:         Mutation,
+    pub selection:        Selection,
+    /// エリートのうち解けた個体と解けそうな個体に局所探索を行う(ミーム的 GA)
+    ///
+    /// 無駄手を取り除き、末尾 tail_depth 手を IDA* でより短い手順に置き換えて、
+    /// 遺伝子に符号化し直す。解けていない個体は least_to_solve() が
+    /// tail_depth 以下なら IDA* で残りを埋める。
+    pub memetic:          bool,
+    pub tail_depth:       u32,
+    pub encoding:         Encoding,
+}
+
+impl Default for GeneticConfig {
+    fn default() -> Self {
+        Self {
+            n_cand:           1000,
+            n_elite:          10,
+            weight_copy:      9,
+            weight_crossover: 90,
+            weight_mutate:    1,
+            crossover:        Crossover::TwoPoint,
+            mutation:         Mutation::Point,
+            selection:        Selection::Roulette,
+            memetic:          false,
+            tail_depth:       4,
+            encoding:         Encoding::Relative,
+        }
     }
 }
 
@@ -132,60 +342,214 @@ pub struct GeneticSolver {
     board:   Board,
     max_len: u32,
     n_gene:  u32,
+    config:  GeneticConfig,
     cands:   Vec<Candidate>,
-    rng:     ThreadRng,
+    seed:    u64,
+    rng:     XorShiftRng,
+    log:     Option<PathBuf>,
+    save:    Option<PathBuf>,
     k_ini:   u32,
+    memo:    HashMap<Vec<u8>, Option<Vec<u8>>>,  // 局所探索の結果(改善できなければ None)
+    fitness: Arc<dyn Fitness>,
+    verbose: bool,
+    log_w:   Option<BufWriter<File>>,
+    pop_ini: Option<Population>,
+    sol_ini: Vec<Vec<u8>>,
+    cancel:  Cancel,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum Operation {
-    COPY,
-    CROSSOVER,
-    MUTATE,
+    Copy,
+    Crossover,
+    Mutate,
 }
 
-impl GeneticSolver {
-    const N_CAND:  usize = 1000;
-    const N_ELITE: usize = 10;
+// 親を選ぶ。cands はスコアの降順に並んでいる
+enum Selector {
+    // 累積の重み
+    Roulette(Vec<f64>),
+    Tournament(usize),
+}
+
+impl Selector {
+    fn roulette(cands: &[Candidate]) -> Self {
+        // 負の評価値があれば最小値が 0 になるようにずらす
+        let min = cands.iter().map(|cand| cand.score).fold(0.0, f64::min);
+        let mut sum = 0.0;
+        let cum = cands.iter()
+            .map(|cand| { sum += cand.score - min; sum })
+            .collect();
+        Selector::Roulette(cum)
+    }
+
+    fn select<R: Rng>(&self, rng: &mut R, n_cand: usize) -> usize {
+        match self {
+            Selector::Roulette(cum)  => {
+                let total = cum.last().cloned().unwrap_or(0.0);
+                if total <= 0.0 { return rng.gen_range(0, n_cand); }
+                let x = rng.gen::<f64>() * total;
+                // x より大きい最初の累積値の位置
+                let i = match cum.binary_search_by(|e| e.total_cmp(&x)) {
+                    Ok(i)  => i + 1,
+                    Err(i) => i,
+                };
+                cmp::min(i, n_cand-1)
+            },
+            Selector::Tournament(n)  => {
+                (0..*n)
+                    .map(|_| rng.gen_range(0, n_cand))
+                    .min()
+                    .unwrap()
+            },
+        }
+    }
+}
 
+impl GeneticSolver {
     pub fn new(max_len: u32, n_gene: u32) -> Self {
+        let seed = thread_rng().gen();
         Self {
-            board: Board::new(0, &[0; 64]),
+            board:   Board::new(0, &[0; 64]),
             max_len,
             n_gene,
-            cands: vec![],
-            rng:   thread_rng(),
-            k_ini: 0,
+            config:  GeneticConfig::default(),
+            cands:   vec![],
+            seed,
+            rng:     util::seeded_rng(seed),
+            log:     None,
+            save:    None,
+            k_ini:   0,
+            memo:    HashMap::new(),
+            fitness: Arc::new(PieceFitness),
+            verbose: true,
+            log_w:   None,
+            pop_ini: None,
+            sol_ini: vec![],
+            cancel:  Cancel::new(),
+        }
+    }
+
+    /// 乱数のシードを指定する(デフォルトはランダム)
+    ///
+    /// シードとパラメータが同じなら同じ結果になる。
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self.rng  = util::seeded_rng(seed);
+        self
+    }
+
+    /// cancel が要求されたら次の世代に進む前に探索を打ち切り、それまでの解を SolverError として返す
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// 使っているシード
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// 世代ごとの統計(最良・平均スコア、max_len、解けた個体数)を path に書き出す
+    pub fn log<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.log = Some(path.into());
+        self
+    }
+
+    /// 終了時に最終世代の集団を path に保存する
+    pub fn save<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.save = Some(path.into());
+        self
+    }
+
+    fn write_log<W: Write>(&self, w: &mut W, generation: u32) -> io::Result<()> {
+        let best = self.cands.first().map_or(0.0, |cand| cand.score);
+        let mean = self.cands.iter().map(|cand| cand.score).sum::<f64>() / self.cands.len() as f64;
+        let n_solved = self.cands.iter()
+            .filter(|cand| cand.state == BoardState::Solved)
+            .count();
+        writeln!(w, "{}\t{:.1}\t{:.1}\t{}\t{}", generation, best, mean, self.max_len, n_solved)
+    }
+
+    /// 適応度関数を指定する(デフォルトは PieceFitness)
+    pub fn fitness<F: Fitness + 'static>(mut self, fitness: F) -> Self {
+        self.fitness = Arc::new(fitness);
+        self
+    }
+
+    pub(crate) fn fitness_arc(mut self, fitness: Arc<dyn Fitness>) -> Self {
+        self.fitness = fitness;
+        self
+    }
+
+    /// 保存しておいた集団から始める(足りない分はランダムに作る)
+    ///
+    /// max_len は集団のものの方が小さければそちらに合わせる。局面が異なる集団は無視する。
+    pub fn initial_population(mut self, pop: Population) -> Self {
+        self.pop_ini = Some(pop);
+        self
+    }
+
+    /// 既知の解を遺伝子に符号化して初期集団に入れる
+    pub fn initial_solutions(mut self, sols: Vec<Vec<u8>>) -> Self {
+        self.sol_ini = sols;
+        self
+    }
+
+    /// 現在の集団(評価の良い順)
+    pub fn population(&self) -> Population {
+        Population {
+            board:    self.board.clone(),
+            encoding: self.config.encoding,
+            max_len:  self.max_len,
+            genes:    self.cands.iter().map(|cand| cand.extract(self.max_len)).collect(),
         }
     }
 
+    /// 世代ごとの進捗を stderr に出力するか(デフォルトは true)
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// パラメータを指定する
+    pub fn config(mut self, config: GeneticConfig) -> Self {
+        assert!(config.n_cand > 0 && config.n_elite <= config.n_cand);
+        if let Selection::Tournament(n) = config.selection {
+            assert!(n > 0);
+        }
+        self.config = config;
+        self
+    }
+
     fn evolve(&mut self) {
-        let mut vs = Vec::with_capacity(GeneticSolver::N_CAND);
+        let n_cand  = self.config.n_cand;
+        let n_elite = self.config.n_elite;
+        let mut vs = Vec::with_capacity(n_cand);
 
-        for i in 0..GeneticSolver::N_ELITE {
+        for i in 0..cmp::min(n_elite, self.cands.len()) {
             vs.push(self.cands[i].extract(self.max_len));
         }
 
         let mut ops = vec![
-            Weighted { item: Operation::COPY,      weight:  9 },
-            Weighted { item: Operation::CROSSOVER, weight: 90 },
-            Weighted { item: Operation::MUTATE,    weight:  1 },
+            Weighted { item: Operation::Copy,      weight: self.config.weight_copy },
+            Weighted { item: Operation::Crossover, weight: self.config.weight_crossover },
+            Weighted { item: Operation::Mutate,    weight: self.config.weight_mutate },
         ];
         let ops_wc = WeightedChoice::new(&mut ops);
 
-        let mut select = vec![];
-        for (i,cand) in self.cands.iter().enumerate() {
-            select.push(Weighted { item: i, weight: cand.score });
-        }
-        let select_wc = WeightedChoice::new(&mut select);
+        let selector = match self.config.selection {
+            Selection::Roulette      => Selector::roulette(&self.cands),
+            Selection::Tournament(n) => Selector::Tournament(n),
+        };
 
-        let n_iter = GeneticSolver::N_CAND - GeneticSolver::N_ELITE;
+        let n_iter = n_cand - vs.len();
         for _ in 0..n_iter {
             let op = ops_wc.sample(&mut self.rng);
             let v = match op {
-                Operation::COPY      => self.spawn_copy(&select_wc),
-                Operation::CROSSOVER => self.spawn_crossover(&select_wc),
-                Operation::MUTATE    => self.spawn_mutate(&select_wc),
+                Operation::Copy      => self.spawn_copy(&selector),
+                Operation::Crossover => self.spawn_crossover(&selector),
+                Operation::Mutate    => self.spawn_mutate(&selector),
             };
             vs.push(v);
         }
@@ -193,19 +557,36 @@ impl GeneticSolver {
         self.update_cands(vs);
     }
 
-    fn spawn_copy(&mut self, select_wc: &WeightedChoice<usize>) -> Vec<u8> {
-        let i = select_wc.sample(&mut self.rng);
+    fn select(&mut self, selector: &Selector) -> usize {
+        selector.select(&mut self.rng, self.cands.len())
+    }
+
+    fn spawn_copy(&mut self, selector: &Selector) -> Vec<u8> {
+        let i = self.select(selector);
         self.cands[i].extract(self.max_len)
     }
 
-    fn spawn_crossover(&mut self, select_wc: &WeightedChoice<usize>) -> Vec<u8> {
-        let i1 = select_wc.sample(&mut self.rng);
-        let i2 = select_wc.sample(&mut self.rng);
+    fn spawn_crossover(&mut self, selector: &Selector) -> Vec<u8> {
+        let i1 = self.select(selector);
+        let i2 = self.select(selector);
         let v1 = self.cands[i1].extract(self.max_len);
         let v2 = self.cands[i2].extract(self.max_len);
         debug_assert_eq!(v1.len(), v2.len());
 
-        self.crossover_twopoint(&v1, &v2)
+        match self.config.crossover {
+            Crossover::OnePoint => self.crossover_onepoint(&v1, &v2),
+            Crossover::TwoPoint => self.crossover_twopoint(&v1, &v2),
+            Crossover::Uniform  => self.crossover_uniform(&v1, &v2),
+        }
+    }
+
+    fn crossover_onepoint(&mut self, v1: &[u8], v2: &[u8]) -> Vec<u8> {
+        let mid = self.rng.gen_range(0, v1.len()+1);
+
+        let mut res = Vec::with_capacity(v1.len());
+        res.extend_from_slice(&v1[..mid]);
+        res.extend_from_slice(&v2[mid..]);
+        res
     }
 
     fn crossover_twopoint(&mut self, v1: &[u8], v2: &[u8]) -> Vec<u8> {
@@ -221,34 +602,153 @@ impl GeneticSolver {
         res
     }
 
-    #[allow(dead_code)]
-    #[allow(unused_variables)]
     fn crossover_uniform(&mut self, v1: &[u8], v2: &[u8]) -> Vec<u8> {
-        // TODO: stub
-        vec![]
+        v1.iter().zip(v2)
+            .map(|(&e1, &e2)| if self.rng.gen() { e1 } else { e2 })
+            .collect()
     }
 
-    fn spawn_mutate(&mut self, select_wc: &WeightedChoice<usize>) -> Vec<u8> {
-        let i = select_wc.sample(&mut self.rng);
+    fn spawn_mutate(&mut self, selector: &Selector) -> Vec<u8> {
+        let i = self.select(selector);
         let mut v = self.cands[i].extract(self.max_len);
 
         let j = self.rng.gen_range(0, v.len());
-        v[j] = self.rng.gen();
+        match self.config.mutation {
+            Mutation::Point => {
+                v[j] = self.rng.gen();
+            },
+            Mutation::Swap => {
+                let k = self.rng.gen_range(0, v.len());
+                v.swap(j, k);
+            },
+            Mutation::Insert => {
+                let len = v.len();
+                v.insert(j, self.rng.gen());
+                v.truncate(len);
+            },
+            Mutation::Delete => {
+                v.remove(j);
+                v.push(self.rng.gen());
+            },
+        }
         v
     }
 
+    // 解を遺伝子に符号化する。残りはランダムに埋める
+    fn encode(&mut self, sol: &[u8]) -> Vec<u8> {
+        let mut board = self.board.clone();
+        let mut v: Vec<u8> = sol.iter()
+            .map(|&to| {
+                let e = self.config.encoding.encode(&board, to);
+                board.move_(to);
+                e
+            })
+            .collect();
+        while v.len() < self.max_len as usize {
+            v.push(self.rng.gen());
+        }
+        v
+    }
+
+    // board から tail_depth 手以内で解ける最短手順を IDA* で求める
+    fn complete(&self, board: &Board, max_depth: u32) -> Option<Vec<u8>> {
+        const MAX_NODE_COUNT: u64 = 100_000;
+        if board.is_solved() { return Some(vec![]); }
+        let mut solver = IdaSolver::new(0, MAX_NODE_COUNT)
+            .max_depth(max_depth)
+            .verbose(false);
+        let mut res = None;
+        let _ = solver.solve_stream(board, &mut |_, sol| {
+            res = Some(sol.to_vec());
+            false
+        });
+        res
+    }
+
+    // 解の末尾を IDA* でより短い手順に置き換えていく
+    fn improve_tail(&self, sol: &[u8]) -> Vec<u8> {
+        let mut sol = sol.to_vec();
+        let mut k = 2;
+        while k <= cmp::min(self.config.tail_depth as usize, sol.len()) {
+            let prefix = &sol[..sol.len()-k];
+            let mut board = self.board.clone();
+            for &to in prefix {
+                board.move_(to);
+            }
+            match self.complete(&board, k as u32 - 1) {
+                Some(tail) => {
+                    let mut sol2 = prefix.to_vec();
+                    sol2.extend_from_slice(&tail);
+                    sol = sol2;
+                },
+                None => { k += 1; },
+            }
+        }
+        sol
+    }
+
+    // 局所探索で改善した解を返す。改善できなければ None
+    fn local_search(&self, cand: &Candidate) -> Option<Vec<u8>> {
+        let sol = cand.to_solution();
+        let sol = match cand.state {
+            BoardState::Solved  => sol,
+            BoardState::Playing => {
+                if cand.board.least_to_solve() > self.config.tail_depth { return None; }
+                let tail = self.complete(&cand.board, self.config.tail_depth)?;
+                let mut sol = sol;
+                sol.extend_from_slice(&tail);
+                sol
+            },
+            _ => return None,
+        };
+
+        let sol2 = util::remove_redundant(&self.board, &sol);
+        let sol2 = self.improve_tail(&sol2);
+        if cand.state == BoardState::Solved && sol2.len() >= sol.len() { return None; }
+        if sol2.len() > self.max_len as usize { return None; }
+        Some(sol2)
+    }
+
     fn update_cands(&mut self, vs: Vec<Vec<u8>>) {
         //let mut cands = Vec::with_capacity(GeneticSolver::N_CAND);
         self.cands.clear();
         for v in vs {
-            let cand = Candidate::new(&self.board, v, self.k_ini);
+            let cand = Candidate::new(&self.board, v, self.k_ini, &*self.fitness, self.config.encoding);
             if cand.state == BoardState::Solved {
                 self.max_len = cmp::min(self.max_len, cand.rotate);
             }
             self.cands.push(cand);
         }
         //self.cands = cands;
-        self.cands.sort_unstable_by_key(|cand| cmp::Reverse(cand.score));
+        self.cands.sort_unstable_by(|a, b| b.score.total_cmp(&a.score));
+
+        // 全個体に局所探索をかけると同じ系統ばかりになるので、エリートだけ改善する
+        if self.config.memetic {
+            for i in 0..cmp::min(self.config.n_elite, self.cands.len()) {
+                self.improve(i);
+            }
+            self.cands.sort_unstable_by(|a, b| b.score.total_cmp(&a.score));
+        }
+    }
+
+    fn improve(&mut self, i: usize) {
+        let key = self.cands[i].extract(self.cands[i].rotate);
+        let improved = match self.memo.get(&key) {
+            Some(improved) => improved.clone(),
+            None => {
+                let improved = self.local_search(&self.cands[i]);
+                self.memo.insert(key, improved.clone());
+                improved
+            },
+        };
+        if let Some(sol) = improved {
+            let v = self.encode(&sol);
+            let cand = Candidate::new(&self.board, v, self.k_ini, &*self.fitness, self.config.encoding);
+            if cand.state == BoardState::Solved {
+                self.max_len = cmp::min(self.max_len, cand.rotate);
+            }
+            self.cands[i] = cand;
+        }
     }
 
     fn random_v(&mut self) -> Vec<u8> {
@@ -258,36 +758,256 @@ impl GeneticSolver {
     }
 }
 
-impl Solver for GeneticSolver {
-    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
+impl GeneticSolver {
+    // 初期集団を作る
+    pub(crate) fn init(&mut self, board: &Board) {
         self.board = board.clone();
         self.k_ini = board.counts().iter()
             .fold(0, |sum,&e| sum + u32::from(e));
+        self.rng = util::seeded_rng(self.seed);
+        self.memo.clear();
 
-        let vs = (0..GeneticSolver::N_CAND)
-            .map(|_| self.random_v())
-            .collect();
+        self.log_w = match self.log {
+            Some(ref path) => match File::create(path) {
+                Ok(file) => Some(BufWriter::new(file)),
+                Err(e)   => { eprintln!("cannot create log: {}", e); None },
+            },
+            None => None,
+        };
+        if let Some(ref mut w) = self.log_w {
+            if let Err(e) = writeln!(w, "# seed={}\n# generation\tbest\tmean\tmax_len\tsolved", self.seed) {
+                eprintln!("cannot write log: {}", e);
+            }
+        }
+
+        let mut vs = self.initial_genes();
+        vs.truncate(self.config.n_cand);
+        while vs.len() < self.config.n_cand {
+            let v = self.random_v();
+            vs.push(v);
+        }
         self.update_cands(vs);
+    }
 
-        for i in 0..self.n_gene {
+    // 保存された集団と既知の解から初期集団の遺伝子を作る
+    fn initial_genes(&mut self) -> Vec<Vec<u8>> {
+        let mut sols = vec![];
+        let mut vs = vec![];
+        if let Some(pop) = self.pop_ini.take() {
+            if pop.board != self.board {
+                eprintln!("population: board mismatch, ignored");
+            }
+            else if pop.encoding != self.config.encoding {
+                self.max_len = cmp::min(self.max_len, pop.max_len);
+                // 一旦手順に直してから符号化し直す
+                for v in &pop.genes {
+                    let cand = Candidate::new(&self.board, v.clone(), self.k_ini, &*self.fitness, pop.encoding);
+                    sols.push(cand.to_solution());
+                }
+            }
+            else {
+                self.max_len = cmp::min(self.max_len, pop.max_len);
+                vs = pop.genes.clone();
+            }
+            self.pop_ini = Some(pop);
+        }
+        for sol in &self.sol_ini {
+            if util::verify_solution(&self.board, sol) {
+                sols.push(sol.clone());
+            }
+            else {
+                eprintln!("invalid solution ignored: {:?}", sol);
+            }
+        }
+
+        for sol in sols {
+            let v = self.encode(&sol);
+            vs.push(v);
+        }
+        for v in &mut vs {
+            v.truncate(self.max_len as usize);
+            while v.len() < self.max_len as usize {
+                v.push(self.rng.gen());
+            }
+        }
+        vs
+    }
+
+    // 1 世代進める
+    pub(crate) fn generation(&mut self, i: u32) {
+        if self.verbose {
             eprintln!("Generation {}: max_len={}", i, self.max_len);
+        }
+
+        self.evolve();
+        if let Some(mut w) = self.log_w.take() {
+            match self.write_log(&mut w, i) {
+                Ok(())  => { self.log_w = Some(w); },
+                Err(e)  => { eprintln!("cannot write log: {}", e); },
+            }
+        }
+        if cfg!(debug_assertions) && self.verbose {
+            eprintln!("{:?}", &self.cands[..cmp::min(20, self.cands.len())]);
+        }
+    }
+
+    // 上位 n 個体の遺伝子
+    pub(crate) fn emigrants(&self, n: usize) -> Vec<Vec<u8>> {
+        self.cands.iter()
+            .take(n)
+            .map(|cand| cand.extract(self.max_len))
+            .collect()
+    }
+
+    // 下位の個体を vs で置き換える。長さが足りなければランダムに埋める
+    pub(crate) fn immigrate(&mut self, vs: Vec<Vec<u8>>) {
+        let n = cmp::min(vs.len(), self.cands.len());
+        let len = self.cands.len() - n;
+        self.cands.truncate(len);
+        for mut v in vs.into_iter().take(n) {
+            // 移民元の島は max_len が長いことがある
+            v.truncate(self.max_len as usize);
+            while v.len() < self.max_len as usize {
+                v.push(self.rng.gen());
+            }
+            let cand = Candidate::new(&self.board, v, self.k_ini, &*self.fitness, self.config.encoding);
+            if cand.state == BoardState::Solved {
+                self.max_len = cmp::min(self.max_len, cand.rotate);
+            }
+            self.cands.push(cand);
+        }
+        self.cands.sort_by(|a, b| b.score.total_cmp(&a.score));
+    }
+
+    pub(crate) fn get_max_len(&self) -> u32 {
+        self.max_len
+    }
 
-            self.evolve();
-            if cfg!(debug_assertions) {
-                eprintln!("{:?}", &self.cands[..20]);
+    // ログを閉じ、最終世代の解を返す
+    pub(crate) fn finish(&mut self) -> Vec<Vec<u8>> {
+        if let Some(mut w) = self.log_w.take() {
+            if let Err(e) = w.flush() {
+                eprintln!("cannot write log: {}", e);
+            }
+        }
+        if let Some(ref path) = self.save {
+            if let Err(e) = self.population().save(path) {
+                eprintln!("cannot save population: {}", e);
             }
         }
 
         let mut res: Vec<_> = self.cands.iter()
             .filter_map(|cand| {
                 match cand.state {
-                    BoardState::Solved => Some(cand.to_solution(&self.board)),
+                    BoardState::Solved => Some(cand.to_solution()),
                     _                  => None,
                 }
             })
             .collect();
         res.sort();
         res.dedup();
-        Ok(res)
+        res
+    }
+}
+
+impl Solver for GeneticSolver {
+    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
+        // 個体は解けておらず、打てる手がある局面から評価する
+        if board.is_solved() { return Ok(vec![vec![]]); }
+        if board.is_stuck() || board.moves().is_empty() { return Ok(vec![]); }
+
+        self.init(board);
+        for i in 0..self.n_gene {
+            if self.cancel.is_cancelled() { return Err(SolverError::cancelled(self.finish())); }
+            self.generation(i);
+        }
+        Ok(self.finish())
+    }
+}
+
+#[test]
+fn test_genetic_operators() {
+    let mut solver = GeneticSolver::new(8, 1);
+    let v1: Vec<u8> = (0..8).collect();
+    let v2: Vec<u8> = (10..18).collect();
+
+    for _ in 0..100 {
+        for v in &[
+            solver.crossover_onepoint(&v1, &v2),
+            solver.crossover_twopoint(&v1, &v2),
+            solver.crossover_uniform(&v1, &v2),
+        ] {
+            assert_eq!(v1.len(), v.len());
+            for (i, &e) in v.iter().enumerate() {
+                assert!(e == v1[i] || e == v2[i]);
+            }
+        }
+    }
+
+    let board: Board = "\
+1 1
+#######4
+#010...#
+#.<1.^2#
+#.2.1..#
+3......#
+#.v..>.3
+#......v
+#######4
+".parse().unwrap();
+    for &mutation in &[Mutation::Point, Mutation::Swap, Mutation::Insert, Mutation::Delete] {
+        let mut solver = GeneticSolver::new(8, 1).config(GeneticConfig {
+            n_cand:    20,
+            n_elite:   2,
+            mutation,
+            selection: Selection::Tournament(3),
+            ..GeneticConfig::default()
+        });
+        solver.board = board.clone();
+        solver.k_ini = 15;
+        let vs = (0..20).map(|_| solver.random_v()).collect();
+        solver.update_cands(vs);
+        for _ in 0..10 {
+            let v = solver.spawn_mutate(&Selector::Tournament(3));
+            assert_eq!(8, v.len());
+        }
+        solver.evolve();
+        assert_eq!(20, solver.cands.len());
+
+        // 長い移民は max_len に切り詰める
+        solver.immigrate(vec![vec![0; 20], vec![1; 3]]);
+        assert_eq!(20, solver.cands.len());
+        assert!(solver.cands.iter().all(|cand| cand.v.len() == 8));
+    }
+}
+
+#[test]
+fn test_genetic_encoding() {
+    let board: Board = "\
+1 1
+#######4
+#010...#
+#.<1.^2#
+#.2.1..#
+3......#
+#.v..>.3
+#......v
+#######4
+".parse().unwrap();
+    assert_eq!(vec![18,21,42,45,55], board.moves());
+
+    assert_eq!(42, Encoding::Relative.decode(&board, 2));
+    assert_eq!(42, Encoding::Relative.decode(&board, 7));
+    assert_eq!(18, Encoding::Absolute.decode(&board, 18));
+    assert_eq!(45, Encoding::Absolute.decode(&board, 64+45));
+    // 合法手でなければ最も近い合法手に修正する
+    assert_eq!(18, Encoding::Absolute.decode(&board, 19));
+    assert_eq!(18, Encoding::Absolute.decode(&board, 0));
+    assert_eq!(55, Encoding::Absolute.decode(&board, 63));
+
+    for &encoding in &[Encoding::Relative, Encoding::Absolute] {
+        for to in board.moves() {
+            assert_eq!(to, encoding.decode(&board, encoding.encode(&board, to)));
+        }
     }
 }
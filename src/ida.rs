@@ -1,87 +1,569 @@
+use std::collections::{ HashSet };
+use std::fs;
 use std::io::{ self, prelude::* };
+use std::path::{ PathBuf };
+use std::sync::{ mpsc, Mutex, atomic::{ AtomicBool, AtomicU64, AtomicUsize, Ordering } };
+use std::thread;
 use std::time;
 
-use itertools::{ Itertools };
-
-use ::{ Solver, SolverError };
+use ::{ Cancel, DepthStats, Solver, SolverError, StreamSolver };
 use board::{ Board };
+use checkpoint::{ Checkpoint };
 use util;
 
 pub struct IdaSolver {
     board:          Board,
-    solutions:      Vec<Vec<u8>>,
     max_depth:      u32,
     max_depth_ini:  u32,
+    depth_limit:    u32,
     node_count:     u64,
+    total_count:    u64,       // solve 全体で調べたノード数
+    stats:          Vec<DepthStats>,
     max_node_count: u64,
+    n_thread:       usize,
+    heuristic:      bool,
+    reduce_order:   bool,
+    expand_order:   bool,
+    verbose:        bool,
+    checkpoint:     Option<(PathBuf, time::Duration)>,
+    resume:         Option<Checkpoint>,
+    cancel:         Cancel,
+}
+
+// 1 回の反復(max_depth 固定)の間、全スレッドで共有する状態
+struct Shared<'a> {
+    board:          &'a Board,
+    max_depth:      u32,
+    max_node_count: u64,
+    heuristic:      bool,
+    reduce_order:   bool,
+    node_count:     AtomicU64,
+    aborted:        AtomicBool,
+    solutions:      Mutex<Vec<Vec<u8>>>,  // この反復で見つけた解(チェックポイント用)
+    checkpoint:     Option<&'a (PathBuf, time::Duration)>,
+    checkpoint_t:   Mutex<time::Instant>,
+    cancel:         &'a Cancel,
+}
+
+impl<'a> Shared<'a> {
+    // 手順 progress より前の部分木を探索し終えたときに呼ぶ
+    fn on_progress(&self, progress: &[u32]) {
+        let (path, interval) = match self.checkpoint {
+            Some(&(ref path, interval)) => (path, interval),
+            None                        => return,
+        };
+        // 打ち切られた部分木は探索し終えていない
+        if self.aborted.load(Ordering::Relaxed) || self.cancel.is_cancelled() { return; }
+        let node_count = self.node_count.load(Ordering::Relaxed);
+        if node_count > self.max_node_count { return; }
+        let mut t = self.checkpoint_t.lock().unwrap();
+        if t.elapsed() < interval { return; }
+
+        let ckpt = Checkpoint {
+            board:      self.board.clone(),
+            max_depth:  self.max_depth,
+            node_count,
+            progress:   progress.to_vec(),
+            solutions:  self.solutions.lock().unwrap().clone(),
+        };
+        if let Err(e) = ckpt.save(path) {
+            eprintln!("cannot save checkpoint: {}", e);
+        }
+        *t = time::Instant::now();
+    }
+}
+
+// ノードを訪れた結果
+enum Node {
+    Solved,
+    Leaf,
+    Inner(Vec<u8>),
+}
+
+// 並列探索で 1 つのスレッドが探索する部分木
+struct Subtree {
+    board:  Board,
+    parent: Option<Board>,
+    depth:  u32,
+    sol:    Vec<u8>,
+    path:   Vec<u32>,          // 初期局面から子の番号をたどった手順
+    resume: Option<Vec<u32>>,  // 再開時に読み飛ばす範囲
+}
+
+// 並列探索の作業単位。直列版の DFS で訪れる順に並べる
+// Solution は (解, path)
+enum Task {
+    Solution(Vec<u8>, Vec<u32>),
+    Subtree(Subtree),
+}
+
+impl Task {
+    fn path(&self) -> &[u32] {
+        match self {
+            Task::Solution(_, path) => path,
+            Task::Subtree(subtree)  => &subtree.path,
+        }
+    }
+}
+
+// resume: このノードの部分木のうち、この手順より前は探索済み
+// i 番目の子について、読み飛ばすなら None、探索するなら Some(子の resume) を返す
+fn resume_child(resume: Option<&[u32]>, i: u32) -> Option<Option<&[u32]>> {
+    match resume {
+        Some(r) if !r.is_empty() => {
+            if i < r[0]       { None }
+            else if i == r[0] { Some(if r.len() > 1 { Some(&r[1..]) } else { None }) }
+            else              { Some(None) }
+        },
+        _ => Some(None),
+    }
+}
+
+// 解 sol の連続する 2 手を Board::commutes() な限り入れ替えて得られる解を全て返す(先頭は sol)
+fn expand_commuted(board: &Board, sol: &[u8]) -> Vec<Vec<u8>> {
+    let mut res  = vec![sol.to_vec()];
+    let mut seen = HashSet::new();
+    seen.insert(sol.to_vec());
+    let mut k = 0;
+    while k < res.len() {
+        let sol = res[k].clone();
+        k += 1;
+        let mut board = board.clone();
+        for i in 0..sol.len().saturating_sub(1) {
+            if board.commutes(sol[i], sol[i+1]) {
+                let mut sol2 = sol.clone();
+                sol2.swap(i, i+1);
+                if seen.insert(sol2.clone()) { res.push(sol2); }
+            }
+            board.move_(sol[i]);
+        }
+    }
+    res
+}
+
+// スレッドごとの探索状態
+struct Search<'a, 'b: 'a> {
+    shared:     &'a Shared<'b>,
+    path:       Vec<u32>,
+    checkpoint: bool,
+}
+
+impl<'a, 'b> Search<'a, 'b> {
+    const CHECKPOINT_DEPTH: u32 = 6;
+
+    fn new(shared: &'a Shared<'b>) -> Self {
+        Self {
+            shared,
+            path:       vec![],
+            checkpoint: false,
+        }
+    }
+
+    // ノード数は訪れるたびに全スレッドで共有するカウンタに足す
+    // 並列版でも、ある反復のノード数が上限以下なら枝を刈らず、直列版と同じ解を返す
+    fn visit(&mut self, board: &Board, depth: u32) -> Node {
+        let node_count = self.shared.node_count.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if board.is_solved() { return Node::Solved; }
+        if depth + 1 > self.shared.max_depth { return Node::Leaf; }
+        if board.is_stuck() { return Node::Leaf; }
+        let moves = board.moves();
+        if moves.is_empty() { return Node::Leaf; }
+        if self.shared.heuristic && depth + board.least_to_solve() > self.shared.max_depth { return Node::Leaf; }
+        if node_count > self.shared.max_node_count { return Node::Leaf; }
+        if self.shared.cancel.is_cancelled() { return Node::Leaf; }
+
+        Node::Inner(moves)
+    }
+
+    // 直前の手と入れ替えても同じ結果になる手のうち、番号が小さい方を後に打つ順序は探索しない
+    fn is_reduced(&self, parent: Option<&Board>, sol: &[u8], to: u8) -> bool {
+        if !self.shared.reduce_order { return false; }
+        match (parent, sol.last()) {
+            (Some(parent), Some(&prev)) => to < prev && parent.commutes(prev, to),
+            _                           => false,
+        }
+    }
+
+    fn dfs(&mut self, board: &Board, parent: Option<&Board>, depth: u32, sol: &[u8], resume: Option<&[u32]>, f: &mut dyn FnMut(u32, &[u8]) -> bool) {
+        match self.visit(board, depth) {
+            Node::Solved => {
+                if !f(depth, sol) { self.shared.aborted.store(true, Ordering::Relaxed); }
+            },
+            Node::Leaf => {},
+            Node::Inner(moves) => {
+                for (i, to) in moves.into_iter().enumerate() {
+                    let i = i as u32;
+                    let resume = match resume_child(resume, i) {
+                        Some(resume) => resume,
+                        None         => continue,
+                    };
+                    if self.is_reduced(parent, sol, to) { continue; }
+                    if self.shared.aborted.load(Ordering::Relaxed) { return; }
+                    let mut board2 = board.clone();
+                    board2.move_(to);
+                    let mut sol2 = sol.to_vec();
+                    sol2.push(to);
+                    self.path.push(i);
+                    self.dfs(&board2, Some(board), depth+1, &sol2, resume, f);
+                    self.path.pop();
+
+                    if self.checkpoint && depth < Search::CHECKPOINT_DEPTH {
+                        self.path.push(i+1);
+                        self.shared.on_progress(&self.path);
+                        self.path.pop();
+                    }
+                }
+            },
+        }
+    }
+
+    // 部分木が n_task 個以上になるまで浅い方から展開する
+    fn split(&mut self, board: &Board, resume: Option<&[u32]>, n_task: usize) -> Vec<Task> {
+        let mut tasks = vec![Task::Subtree(Subtree {
+            board:  board.clone(),
+            parent: None,
+            depth:  0,
+            sol:    vec![],
+            path:   vec![],
+            resume: resume.map(|r| r.to_vec()),
+        })];
+        loop {
+            let n_subtree = tasks.iter()
+                .filter(|task| matches!(task, Task::Subtree(..)))
+                .count();
+            if n_subtree == 0 || n_subtree >= n_task { break; }
+
+            let mut tasks_next = Vec::with_capacity(tasks.len());
+            for task in tasks {
+                let Subtree { board, parent, depth, sol, path, resume } = match task {
+                    Task::Subtree(subtree) => subtree,
+                    _ => { tasks_next.push(task); continue; },
+                };
+                match self.visit(&board, depth) {
+                    Node::Solved => tasks_next.push(Task::Solution(sol, path)),
+                    Node::Leaf => {},
+                    Node::Inner(moves) => {
+                        for (i, to) in moves.into_iter().enumerate() {
+                            let i = i as u32;
+                            let resume2 = match resume_child(resume.as_ref().map(|r| &r[..]), i) {
+                                Some(resume2) => resume2.map(|r| r.to_vec()),
+                                None          => continue,
+                            };
+                            if self.is_reduced(parent.as_ref(), &sol, to) { continue; }
+                            let mut board2 = board.clone();
+                            board2.move_(to);
+                            let mut sol2 = sol.clone();
+                            sol2.push(to);
+                            let mut path2 = path.clone();
+                            path2.push(i);
+                            tasks_next.push(Task::Subtree(Subtree {
+                                board:  board2,
+                                parent: Some(board.clone()),
+                                depth:  depth+1,
+                                sol:    sol2,
+                                path:   path2,
+                                resume: resume2,
+                            }));
+                        }
+                    },
+                }
+            }
+            tasks = tasks_next;
+        }
+        tasks
+    }
 }
 
 impl IdaSolver {
+    const N_TASK_PER_THREAD: usize = 16;
+
     pub fn new(max_depth_ini: u32, max_node_count: u64) -> Self {
         Self {
-            board:      Board::new(0, &[0; 64]),
-            solutions:  vec![],
-            max_depth:  0,
+            board:        Board::new(0, &[0; 64]),
+            max_depth:    0,
             max_depth_ini,
-            node_count: 0,
+            depth_limit:  u32::MAX,
+            node_count:   0,
+            total_count:  0,
+            stats:        vec![],
             max_node_count,
+            n_thread:     1,
+            heuristic:    true,
+            reduce_order: false,
+            expand_order: false,
+            verbose:      true,
+            checkpoint:   None,
+            resume:       None,
+            cancel:       Cancel::new(),
         }
     }
 
-    fn dfs(&mut self, board: &Board, depth: u32, sol: &Vec<u8>) {
-        self.node_count += 1;
-        if board.is_solved() {
-            eprintln!("{}", sol.iter().join(" "));
-            self.solutions.push(sol.clone());
-            return;
+    /// 探索に使うスレッド数を指定する(デフォルトは 1)
+    ///
+    /// 並列版も直列版と全く同じ解を同じ順序で返す。
+    pub fn threads(mut self, n_thread: usize) -> Self {
+        assert!(n_thread > 0);
+        self.n_thread = n_thread;
+        self
+    }
+
+    /// この深さまでに解が見つからなければ、解なしとして探索を終える
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.depth_limit = max_depth;
+        self
+    }
+
+    /// 反復ごとの進捗を stderr に出力するか(デフォルトは true)
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    // least_to_solve() による枝刈りの有無(無しなら単なる反復深化)
+    pub(crate) fn heuristic(mut self, heuristic: bool) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    /// 入れ替えても結果が変わらない連続する 2 手(Board::commutes())は一方の順序だけ探索する
+    ///
+    /// expand が偽なら、そうした手順の違う解のうち 1 つだけを返す。
+    /// 真なら、見つけた解の手を入れ替えて省いた解を復元し、枝刈りしない場合と同じ解の集合を返す。
+    pub fn reduce_order(mut self, expand: bool) -> Self {
+        self.reduce_order = true;
+        self.expand_order = expand;
+        self
+    }
+
+    /// 探索中、interval 以上の間隔で path にチェックポイントを書き出す
+    ///
+    /// 探索が正常に終わったらチェックポイントファイルは削除する。
+    pub fn checkpoint<P: Into<PathBuf>>(mut self, path: P, interval: time::Duration) -> Self {
+        self.checkpoint = Some((path.into(), interval));
+        self
+    }
+
+    /// cancel が要求されたら探索を打ち切り、それまでの解を SolverError として返す
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// 次の solve でチェックポイントから探索を再開する
+    ///
+    /// チェックポイントまでに見つかっていた解も改めて返す。
+    /// 渡された局面がチェックポイントのものと異なれば最初から探索する。
+    pub fn resume(mut self, ckpt: Checkpoint) -> Self {
+        self.resume = Some(ckpt);
+        self
+    }
+
+    // max_depth を固定して 1 回探索し、見つけた解の数を返す
+    fn search(&mut self, resume: Option<Checkpoint>, f: &mut dyn FnMut(u32, &[u8]) -> bool) -> usize {
+        let (node_count, progress, solutions) = match resume {
+            Some(ckpt) => (ckpt.node_count, Some(ckpt.progress), ckpt.solutions),
+            None       => (0, None, vec![]),
+        };
+        let shared = Shared {
+            board:          &self.board,
+            max_depth:      self.max_depth,
+            max_node_count: self.max_node_count,
+            heuristic:      self.heuristic,
+            reduce_order:   self.reduce_order,
+            node_count:     AtomicU64::new(node_count),
+            aborted:        AtomicBool::new(false),
+            solutions:      Mutex::new(vec![]),
+            checkpoint:     self.checkpoint.as_ref(),
+            checkpoint_t:   Mutex::new(time::Instant::now()),
+            cancel:         &self.cancel,
+        };
+
+        let mut n_solution = 0;
+        {
+            let mut f = |depth: u32, sol: &[u8]| {
+                n_solution += 1;
+                if shared.checkpoint.is_some() {
+                    shared.solutions.lock().unwrap().push(sol.to_vec());
+                }
+                f(depth, sol)
+            };
+
+            // チェックポイントまでに見つかっていた解
+            for sol in solutions {
+                if !f(sol.len() as u32, &sol) {
+                    shared.aborted.store(true, Ordering::Relaxed);
+                    break;
+                }
+            }
+
+            let progress = progress.as_ref().map(|p| &p[..]);
+            if !shared.aborted.load(Ordering::Relaxed) {
+                if self.n_thread == 1 {
+                    let mut search = Search::new(&shared);
+                    search.checkpoint = true;
+                    search.dfs(&self.board, None, 0, &[], progress, &mut f);
+                }
+                else {
+                    IdaSolver::search_parallel(&shared, progress, self.n_thread, &mut f);
+                }
+            }
         }
-        if depth + 1 > self.max_depth { return; }
-        if board.is_stuck() { return; }
-        let moves = board.moves();
-        if moves.is_empty() { return; }
-        if depth + board.least_to_solve() > self.max_depth { return; }
-        if self.node_count > self.max_node_count { return; }
-
-        for to in moves {
-            let mut board2 = board.clone();
-            board2.move_(to);
-            let mut sol2 = sol.clone();
-            sol2.push(to);
-            self.dfs(&board2, depth+1, &sol2);
+
+        self.node_count = shared.node_count.load(Ordering::Relaxed);
+        n_solution
+    }
+
+    fn search_parallel(shared: &Shared, resume: Option<&[u32]>, n_thread: usize, f: &mut dyn FnMut(u32, &[u8]) -> bool) {
+        let tasks = Search::new(shared).split(shared.board, resume, IdaSolver::N_TASK_PER_THREAD * n_thread);
+        let next = AtomicUsize::new(0);
+        let (tx, rx) = mpsc::channel();
+
+        thread::scope(|s| {
+            for _ in 0..n_thread {
+                let tx = tx.clone();
+                let tasks = &tasks;
+                let next = &next;
+                s.spawn(move || {
+                    let mut search = Search::new(shared);
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        if i >= tasks.len() { break; }
+                        if let Task::Subtree(ref t) = tasks[i] {
+                            let mut sols = vec![];
+                            search.path = t.path.clone();
+                            search.dfs(&t.board, t.parent.as_ref(), t.depth, &t.sol, t.resume.as_ref().map(|r| &r[..]), &mut |_, sol| {
+                                sols.push(sol.to_vec());
+                                true
+                            });
+                            if tx.send((i, sols)).is_err() { break; }
+                        }
+                    }
+                });
+            }
+            drop(tx);
+
+            // 解はタスクの順に渡す(直列版と同じ順序になる)
+            // チェックポイントの progress も、渡し終えたタスクの次のタスクとする
+            let mut results = vec![None; tasks.len()];
+            for (i, task) in tasks.iter().enumerate() {
+                let sols = match task {
+                    Task::Solution(sol, _) => vec![sol.clone()],
+                    Task::Subtree(..) => {
+                        while results[i].is_none() {
+                            let (j, sols) = rx.recv().expect("worker thread panicked");
+                            results[j] = Some(sols);
+                        }
+                        results[i].take().unwrap()
+                    },
+                };
+                for sol in sols {
+                    if shared.aborted.load(Ordering::Relaxed) { continue; }
+                    if !f(sol.len() as u32, &sol) { shared.aborted.store(true, Ordering::Relaxed); }
+                }
+                if i+1 < tasks.len() {
+                    shared.on_progress(tasks[i+1].path());
+                }
+            }
+        });
+    }
+
+    // max_depth の反復を最初から始めるところのチェックポイントを書き出す
+    fn save_checkpoint(&self) {
+        if let Some((ref path, _)) = self.checkpoint {
+            let ckpt = Checkpoint {
+                board:      self.board.clone(),
+                max_depth:  self.max_depth,
+                node_count: 0,
+                progress:   vec![],
+                solutions:  vec![],
+            };
+            if let Err(e) = ckpt.save(path) {
+                eprintln!("cannot save checkpoint: {}", e);
+            }
         }
     }
 }
 
-impl Solver for IdaSolver {
-    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
-        self.board     = board.clone();
-        self.solutions = vec![];
-        self.max_depth = self.max_depth_ini;
+impl StreamSolver for IdaSolver {
+    fn solve_stream(&mut self, board: &Board, f: &mut dyn FnMut(u32, &[u8]) -> bool) -> Result<(),SolverError> {
+        self.board       = board.clone();
+        self.max_depth   = self.max_depth_ini;
+        self.total_count = 0;
+        self.stats       = vec![];
 
-        loop {
-            self.node_count = 0;
+        let mut resume = self.resume.take().filter(|ckpt| ckpt.board == *board);
+        if let Some(ref ckpt) = resume {
+            self.max_depth = ckpt.max_depth;
+        }
 
-            eprint!("Depth {}: ", self.max_depth);
-            io::stderr().flush().unwrap();
+        let mut aborted = false;
+        loop {
+            if self.verbose {
+                eprint!("Depth {}: ", self.max_depth);
+                io::stderr().flush().unwrap();
+            }
             let t = time::Instant::now();
 
-            let board = self.board.clone();
-            self.dfs(&board, 0, &vec![]);
+            let expand_order = self.expand_order;
+            let mut emitted = HashSet::new();
+            let n_solution = self.search(resume.take(), &mut |depth, sol| {
+                if expand_order {
+                    for sol in expand_commuted(board, sol) {
+                        if !emitted.insert(sol.clone()) { continue; }
+                        if !f(depth, &sol) { aborted = true; break; }
+                    }
+                }
+                else if !f(depth, sol) { aborted = true; }
+                !aborted
+            });
+            self.total_count += self.node_count;
 
             let mut dur = util::duration_float(&t.elapsed());
+            self.stats.push(DepthStats { depth: self.max_depth, nodes: self.node_count, time: dur });
             if dur < 1e-3 { dur = 1e-3; }
-            eprintln!("Nodes={}, Time={:.3}, NPS={:.0}",
-                     self.node_count,
-                     dur,
-                     self.node_count as f64 / dur);
+            if self.verbose {
+                eprintln!("Nodes={}, Time={:.3}, NPS={:.0}",
+                         self.node_count,
+                         dur,
+                         self.node_count as f64 / dur);
+            }
 
+            if aborted {
+                return Ok(());
+            }
+            if self.cancel.is_cancelled() {
+                return Err(SolverError::cancelled(vec![]));
+            }
+            // 全スレッドが数えたノード数で判定するので、並列版でも直列版と同じ結果になる
             if self.node_count > self.max_node_count {
-                return Err(SolverError::new(self.solutions.clone()));
+                return Err(SolverError::new(vec![]));
+            }
+            if n_solution > 0 {
+                if let Some((ref path, _)) = self.checkpoint {
+                    let _ = fs::remove_file(path);
+                }
+                return Ok(());
             }
-            if !self.solutions.is_empty() {
-                return Ok(self.solutions.clone());
+            if self.max_depth >= self.depth_limit {
+                return Ok(());
             }
             self.max_depth += 1;
+            self.save_checkpoint();
         }
     }
 }
+
+impl Solver for IdaSolver {
+    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
+        ::collect_solutions(self, board)
+    }
+
+    fn node_count(&self) -> Option<u64> {
+        Some(self.total_count)
+    }
+
+    fn depth_stats(&self) -> Vec<DepthStats> {
+        self.stats.clone()
+    }
+}
@@ -1,86 +1,70 @@
-use std::io::{ self, prelude::* };
+use std::path::{ PathBuf };
 use std::time;
 
-use itertools::{ Itertools };
-
-use ::{ Solver, SolverError };
+use ::{ Cancel, DepthStats, Solver, SolverError, StreamSolver };
 use board::{ Board };
-use util;
+use checkpoint::{ Checkpoint };
+use ida::{ IdaSolver };
 
+// least_to_solve() による枝刈りをしない IDA* として実装する
 pub struct IddfsSolver {
-    board:          Board,
-    solutions:      Vec<Vec<u8>>,
-    max_depth:      u32,
-    max_depth_ini:  u32,
-    node_count:     u64,
-    max_node_count: u64,
+    inner: IdaSolver,
 }
 
 impl IddfsSolver {
     pub fn new(max_depth_ini: u32, max_node_count: u64) -> Self {
         Self {
-            board:      Board::new(0, &[0; 64]),
-            solutions:  vec![],
-            max_depth:  0,
-            max_depth_ini,
-            node_count: 0,
-            max_node_count
+            inner: IdaSolver::new(max_depth_ini, max_node_count).heuristic(false),
         }
     }
 
-    fn dfs(&mut self, board: &Board, depth: u32, sol: &Vec<u8>) {
-        self.node_count += 1;
-        if board.is_solved() {
-            eprintln!("{}", sol.iter().join(" "));
-            self.solutions.push(sol.clone());
-            return;
-        }
-        if depth + 1 > self.max_depth { return; }
-        if board.is_stuck() { return; }
-        let moves = board.moves();
-        if moves.is_empty() { return; }
-        if self.node_count > self.max_node_count { return; }
+    /// IdaSolver::threads() を参照
+    pub fn threads(self, n_thread: usize) -> Self {
+        Self { inner: self.inner.threads(n_thread) }
+    }
 
-        for to in moves {
-            let mut board2 = board.clone();
-            board2.move_(to);
-            let mut sol2 = sol.clone();
-            sol2.push(to);
-            self.dfs(&board2, depth+1, &sol2);
-        }
+    /// IdaSolver::max_depth() を参照
+    pub fn max_depth(self, depth_limit: u32) -> Self {
+        Self { inner: self.inner.max_depth(depth_limit) }
     }
-}
 
-impl Solver for IddfsSolver {
-    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
-        self.board     = board.clone();
-        self.solutions = vec![];
-        self.max_depth = self.max_depth_ini;
+    /// IdaSolver::reduce_order() を参照
+    pub fn reduce_order(self, expand: bool) -> Self {
+        Self { inner: self.inner.reduce_order(expand) }
+    }
 
-        loop {
-            self.node_count = 0;
+    /// IdaSolver::checkpoint() を参照
+    pub fn checkpoint<P: Into<PathBuf>>(self, path: P, interval: time::Duration) -> Self {
+        Self { inner: self.inner.checkpoint(path, interval) }
+    }
 
-            eprint!("Depth {}: ", self.max_depth);
-            io::stderr().flush().unwrap();
-            let t = time::Instant::now();
+    /// IdaSolver::cancel() を参照
+    pub fn cancel(self, cancel: Cancel) -> Self {
+        Self { inner: self.inner.cancel(cancel) }
+    }
 
-            let board = self.board.clone();
-            self.dfs(&board, 0, &vec![]);
+    /// IdaSolver::resume() を参照
+    pub fn resume(self, ckpt: Checkpoint) -> Self {
+        Self { inner: self.inner.resume(ckpt) }
+    }
+}
 
-            let mut dur = util::duration_float(&t.elapsed());
-            if dur < 1e-3 { dur = 1e-3; }
-            eprintln!("Nodes={}, Time={:.3}, NPS={:.0}",
-                     self.node_count,
-                     dur,
-                     self.node_count as f64 / dur);
+impl StreamSolver for IddfsSolver {
+    fn solve_stream(&mut self, board: &Board, f: &mut dyn FnMut(u32, &[u8]) -> bool) -> Result<(),SolverError> {
+        self.inner.solve_stream(board, f)
+    }
+}
 
-            if self.node_count > self.max_node_count {
-                return Err(SolverError::new(self.solutions.clone()));
-            }
-            if !self.solutions.is_empty() {
-                return Ok(self.solutions.clone());
-            }
-            self.max_depth += 1;
-        }
+impl Solver for IddfsSolver {
+    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
+        ::collect_solutions(self, board)
+    }
+
+    fn node_count(&self) -> Option<u64> {
+        self.inner.node_count()
+    }
+
+    fn depth_stats(&self) -> Vec<DepthStats> {
+        self.inner.depth_stats()
     }
 }
@@ -0,0 +1,251 @@
+/*!
+ * 島モデルの遺伝的アルゴリズム
+ *
+ * 複数の集団(島)をそれぞれ別スレッドの GeneticSolver で進化させ、
+ * interval 世代ごとに各島の上位個体を topology に従って他の島へ移住させる。
+ * 移住は全ての島が interval 世代進んだところで行うので、結果はスレッドの実行順によらない。
+ *
+ * 島 i のシードは seed + i。最後に全ての島の解のうち最短のものを返す。
+ *
+ * 保存された集団から始めるときは、個体を順に各島へ配る。
+ */
+
+use std::cmp;
+use std::path::{ PathBuf };
+use std::sync::{ Arc };
+use std::thread;
+
+use rand::{ prelude::* };
+
+use ::{ Cancel, Solver, SolverError };
+use board::{ Board };
+use genetic::{ Fitness, GeneticConfig, GeneticSolver, PieceFitness };
+use population::{ Population };
+
+/// 移住先の決め方
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Topology {
+    /// 島 i から島 i+1 へ(最後の島からは最初の島へ)
+    Ring,
+    /// 全ての島から他の全ての島へ
+    Complete,
+}
+
+pub struct IslandSolver {
+    max_len:   u32,
+    n_gene:    u32,
+    n_island:  usize,
+    config:    GeneticConfig,
+    fitness:   Arc<dyn Fitness>,
+    interval:  u32,
+    n_migrant: usize,
+    topology:  Topology,
+    seed:      u64,
+    pop_ini:   Option<Population>,
+    sol_ini:   Vec<Vec<u8>>,
+    save:      Option<PathBuf>,
+    islands:   Vec<GeneticSolver>,
+    cancel:    Cancel,
+}
+
+impl IslandSolver {
+    pub fn new(max_len: u32, n_gene: u32, n_island: usize) -> Self {
+        assert!(n_island > 0);
+        let config = GeneticConfig::default();
+        let n_migrant = config.n_elite;
+        Self {
+            max_len,
+            n_gene,
+            n_island,
+            config,
+            fitness:   Arc::new(PieceFitness),
+            interval:  10,
+            n_migrant,
+            topology:  Topology::Ring,
+            seed:      thread_rng().gen(),
+            pop_ini:   None,
+            sol_ini:   vec![],
+            save:      None,
+            islands:   vec![],
+            cancel:    Cancel::new(),
+        }
+    }
+
+    /// 各島のパラメータを指定する
+    pub fn config(mut self, config: GeneticConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 適応度関数を指定する(デフォルトは PieceFitness)
+    pub fn fitness<F: Fitness + 'static>(mut self, fitness: F) -> Self {
+        self.fitness = Arc::new(fitness);
+        self
+    }
+
+    /// interval 世代ごとに上位 n_migrant 個体を移住させる(デフォルトは 10 世代ごとに 10 個体)
+    pub fn migration(mut self, interval: u32, n_migrant: usize) -> Self {
+        assert!(interval > 0);
+        self.interval  = interval;
+        self.n_migrant = n_migrant;
+        self
+    }
+
+    /// 移住先の決め方を指定する(デフォルトは Ring)
+    pub fn topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// 乱数のシードを指定する(デフォルトはランダム)
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// 保存しておいた集団から始める
+    pub fn initial_population(mut self, pop: Population) -> Self {
+        self.pop_ini = Some(pop);
+        self
+    }
+
+    /// 既知の解を遺伝子に符号化して全ての島の初期集団に入れる
+    pub fn initial_solutions(mut self, sols: Vec<Vec<u8>>) -> Self {
+        self.sol_ini = sols;
+        self
+    }
+
+    /// 終了時に全ての島の最終世代を集めた集団を path に保存する
+    pub fn save<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.save = Some(path.into());
+        self
+    }
+
+    /// cancel が要求されたら次の移住までに探索を打ち切り、それまでの解を SolverError として返す
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// 直前の solve の最終世代を全ての島から集めた集団
+    ///
+    /// 各島の個体を 1 つずつ順に並べる。max_len は全ての島の最小値。
+    pub fn population(&self) -> Option<Population> {
+        let pops: Vec<_> = self.islands.iter().map(|island| island.population()).collect();
+        let first = pops.first()?;
+        let n = pops.iter().map(|pop| pop.genes.len()).max().unwrap_or(0);
+        let genes = (0..n)
+            .flat_map(|i| pops.iter().filter_map(move |pop| pop.genes.get(i).cloned()))
+            .collect();
+        Some(Population {
+            board:    first.board.clone(),
+            encoding: first.encoding,
+            max_len:  pops.iter().map(|pop| pop.max_len).min().unwrap(),
+            genes,
+        })
+    }
+
+    /// 使っているシード
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    // 島 src からの移住先
+    fn destinations(&self, src: usize) -> Vec<usize> {
+        match self.topology {
+            Topology::Ring     => vec![(src + 1) % self.n_island],
+            Topology::Complete => (0..self.n_island).filter(|&dst| dst != src).collect(),
+        }
+    }
+
+    fn migrate(&self, islands: &mut [GeneticSolver]) {
+        let emigrants: Vec<_> = islands.iter()
+            .map(|island| island.emigrants(self.n_migrant))
+            .collect();
+        let mut immigrants = vec![vec![]; self.n_island];
+        for (src, vs) in emigrants.into_iter().enumerate() {
+            for dst in self.destinations(src) {
+                if dst == src { continue; }
+                immigrants[dst].extend(vs.iter().cloned());
+            }
+        }
+        for (island, vs) in islands.iter_mut().zip(immigrants) {
+            island.immigrate(vs);
+        }
+    }
+}
+
+impl Solver for IslandSolver {
+    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
+        // 個体は解けておらず、打てる手がある局面から評価する
+        if board.is_solved() { return Ok(vec![vec![]]); }
+        if board.is_stuck() || board.moves().is_empty() { return Ok(vec![]); }
+
+        let mut islands: Vec<_> = (0..self.n_island)
+            .map(|i| {
+                let mut island = GeneticSolver::new(self.max_len, self.n_gene)
+                    .config(self.config.clone())
+                    .fitness_arc(Arc::clone(&self.fitness))
+                    .seed(self.seed.wrapping_add(i as u64))
+                    .initial_solutions(self.sol_ini.clone())
+                    .verbose(false);
+                if let Some(ref pop) = self.pop_ini {
+                    let genes = pop.genes.iter()
+                        .skip(i)
+                        .step_by(self.n_island)
+                        .cloned()
+                        .collect();
+                    island = island.initial_population(Population {
+                        board:    pop.board.clone(),
+                        encoding: pop.encoding,
+                        max_len:  pop.max_len,
+                        genes,
+                    });
+                }
+                island
+            })
+            .collect();
+
+        let mut gen = 0;
+        while gen < self.n_gene {
+            let n = cmp::min(self.interval, self.n_gene - gen);
+            thread::scope(|s| {
+                for island in islands.iter_mut() {
+                    s.spawn(move || {
+                        if gen == 0 {
+                            island.init(board);
+                        }
+                        for i in gen..gen+n {
+                            island.generation(i);
+                        }
+                    });
+                }
+            });
+            gen += n;
+            if self.cancel.is_cancelled() { break; }
+
+            let max_lens: Vec<_> = islands.iter().map(|island| island.get_max_len()).collect();
+            eprintln!("Generation {}: max_len={:?}", gen, max_lens);
+            if gen < self.n_gene {
+                self.migrate(&mut islands);
+            }
+        }
+
+        let mut sols: Vec<_> = islands.iter_mut()
+            .flat_map(|island| island.finish())
+            .collect();
+        self.islands = islands;
+        if let (Some(path), Some(pop)) = (self.save.as_ref(), self.population()) {
+            if let Err(e) = pop.save(path) {
+                eprintln!("cannot save population: {}", e);
+            }
+        }
+        if let Some(len) = sols.iter().map(|sol| sol.len()).min() {
+            sols.retain(|sol| sol.len() == len);
+        }
+        sols.sort();
+        sols.dedup();
+        if self.cancel.is_cancelled() { return Err(SolverError::cancelled(sols)); }
+        Ok(sols)
+    }
+}
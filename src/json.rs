@@ -0,0 +1,344 @@
+/*!
+ * 最小限の JSON
+ *
+ * 出力用に Value を組み立てて Display で書き出す。オブジェクトのキーは追加した順に並ぶ。
+ * 入力は FromStr で読む(server の要求用)。数は整数なら Int、それ以外は Float になる。
+ */
+
+use std::fmt;
+use std::str::{ FromStr };
+
+use failure;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String,Value)>),
+}
+
+impl Value {
+    /// 空のオブジェクト
+    pub fn object() -> Self {
+        Value::Object(vec![])
+    }
+
+    /// オブジェクトにキーを追加したものを返す
+    pub fn with<V: Into<Value>>(mut self, key: &str, value: V) -> Self {
+        match self {
+            Value::Object(ref mut entries) => entries.push((key.to_owned(), value.into())),
+            _ => panic!("json: not an object"),
+        }
+        self
+    }
+
+    /// オブジェクトのキー key の値。オブジェクトでないかキーが無ければ None
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Object(ref entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Int(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match *self {
+            Value::Array(ref v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self { Value::Bool(b) }
+}
+
+impl From<u8> for Value {
+    fn from(x: u8) -> Self { Value::Int(i64::from(x)) }
+}
+
+impl From<u32> for Value {
+    fn from(x: u32) -> Self { Value::Int(i64::from(x)) }
+}
+
+impl From<u64> for Value {
+    fn from(x: u64) -> Self { Value::Int(x as i64) }
+}
+
+impl From<usize> for Value {
+    fn from(x: usize) -> Self { Value::Int(x as i64) }
+}
+
+impl From<f64> for Value {
+    fn from(x: f64) -> Self { Value::Float(x) }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(s: &'a str) -> Self { Value::String(s.to_owned()) }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self { Value::String(s) }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(x: Option<T>) -> Self {
+        x.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(v: Vec<T>) -> Self {
+        Value::Array(v.into_iter().map(Into::into).collect())
+    }
+}
+
+fn write_str(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"'  => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c    => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Null      => f.write_str("null"),
+            Value::Bool(b)   => write!(f, "{}", b),
+            Value::Int(x)    => write!(f, "{}", x),
+            // JSON には NaN や無限大が無い
+            Value::Float(x)  => if x.is_finite() { write!(f, "{}", x) } else { f.write_str("null") },
+            Value::String(ref s) => write_str(f, s),
+            Value::Array(ref v)  => {
+                f.write_str("[")?;
+                for (i, e) in v.iter().enumerate() {
+                    if i > 0 { f.write_str(",")?; }
+                    write!(f, "{}", e)?;
+                }
+                f.write_str("]")
+            },
+            Value::Object(ref entries) => {
+                f.write_str("{")?;
+                for (i, (k, v)) in entries.iter().enumerate() {
+                    if i > 0 { f.write_str(",")?; }
+                    write_str(f, k)?;
+                    write!(f, ":{}", v)?;
+                }
+                f.write_str("}")
+            },
+        }
+    }
+}
+
+// 再帰下降の構文解析器
+struct Parser<'a> {
+    s: &'a [u8],
+    i: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_ws(&mut self) {
+        while self.i < self.s.len() && b" \t\r\n".contains(&self.s[self.i]) {
+            self.i += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_ws();
+        self.s.get(self.i).cloned()
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), failure::Error> {
+        if self.peek() != Some(c) {
+            bail!("json: expected '{}' at {}", c as char, self.i);
+        }
+        self.i += 1;
+        Ok(())
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, failure::Error> {
+        if !self.s[self.i..].starts_with(word.as_bytes()) {
+            bail!("json: unexpected token at {}", self.i);
+        }
+        self.i += word.len();
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Value, failure::Error> {
+        match self.peek() {
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'"') => Ok(Value::String(self.string()?)),
+            Some(b'[') => {
+                self.i += 1;
+                let mut v = vec![];
+                if self.peek() == Some(b']') {
+                    self.i += 1;
+                    return Ok(Value::Array(v));
+                }
+                loop {
+                    v.push(self.value()?);
+                    if self.peek() == Some(b']') { self.i += 1; break; }
+                    self.expect(b',')?;
+                }
+                Ok(Value::Array(v))
+            },
+            Some(b'{') => {
+                self.i += 1;
+                let mut entries = vec![];
+                if self.peek() == Some(b'}') {
+                    self.i += 1;
+                    return Ok(Value::Object(entries));
+                }
+                loop {
+                    if self.peek() != Some(b'"') { bail!("json: expected a key at {}", self.i); }
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    entries.push((key, self.value()?));
+                    if self.peek() == Some(b'}') { self.i += 1; break; }
+                    self.expect(b',')?;
+                }
+                Ok(Value::Object(entries))
+            },
+            Some(c) if c == b'-' || c.is_ascii_digit() => self.number(),
+            Some(_) => bail!("json: unexpected character at {}", self.i),
+            None    => bail!("json: unexpected end of input"),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, failure::Error> {
+        let start = self.i;
+        while self.i < self.s.len() && b"+-0123456789.eE".contains(&self.s[self.i]) {
+            self.i += 1;
+        }
+        let s = ::std::str::from_utf8(&self.s[start..self.i])?;
+        if let Ok(x) = s.parse() {
+            return Ok(Value::Int(x));
+        }
+        match s.parse() {
+            Ok(x)  => Ok(Value::Float(x)),
+            Err(_) => bail!("json: invalid number: {}", s),
+        }
+    }
+
+    // 開きの '"' の位置から読む
+    fn string(&mut self) -> Result<String, failure::Error> {
+        self.i += 1;
+        let mut buf = vec![];
+        loop {
+            let c = match self.s.get(self.i) {
+                Some(&c) => c,
+                None     => bail!("json: unterminated string"),
+            };
+            self.i += 1;
+            match c {
+                b'"'  => break,
+                b'\\' => {
+                    let e = match self.s.get(self.i) {
+                        Some(&e) => e,
+                        None     => bail!("json: unterminated string"),
+                    };
+                    self.i += 1;
+                    let c = match e {
+                        b'"'  => '"',
+                        b'\\' => '\\',
+                        b'/'  => '/',
+                        b'b'  => '\u{8}',
+                        b'f'  => '\u{c}',
+                        b'n'  => '\n',
+                        b'r'  => '\r',
+                        b't'  => '\t',
+                        b'u'  => {
+                            // サロゲートペアは扱わない
+                            let hex = self.s.get(self.i..self.i+4).ok_or_else(|| format_err!("json: invalid escape"))?;
+                            self.i += 4;
+                            let code = u32::from_str_radix(::std::str::from_utf8(hex)?, 16)?;
+                            ::std::char::from_u32(code).ok_or_else(|| format_err!("json: invalid escape"))?
+                        },
+                        _ => bail!("json: invalid escape at {}", self.i),
+                    };
+                    let mut tmp = [0; 4];
+                    buf.extend_from_slice(c.encode_utf8(&mut tmp).as_bytes());
+                },
+                c => buf.push(c),
+            }
+        }
+        Ok(String::from_utf8(buf)?)
+    }
+}
+
+impl FromStr for Value {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { s: s.as_bytes(), i: 0 };
+        let value = parser.value()?;
+        if parser.peek().is_some() {
+            bail!("json: trailing characters at {}", parser.i);
+        }
+        Ok(value)
+    }
+}
+
+#[test]
+fn test_json() {
+    let v = Value::object()
+        .with("name", "a\"b\\\n")
+        .with("moves", vec![1u8, 2, 3])
+        .with("step", None::<u32>)
+        .with("time", 1.5)
+        .with("ok", true);
+    assert_eq!(r#"{"name":"a\"b\\\n","moves":[1,2,3],"step":null,"time":1.5,"ok":true}"#, v.to_string());
+}
+
+#[test]
+fn test_json_parse() {
+    let s = r#"{"name":"a\"b\\\n","moves":[1,2,3],"step":null,"time":1.5,"ok":true}"#;
+    let v: Value = s.parse().unwrap();
+    assert_eq!(s, v.to_string());
+    assert_eq!(Some("a\"b\\\n"), v.get("name").and_then(Value::as_str));
+    assert_eq!(Some(3), v.get("moves").and_then(Value::as_array).map(|a| a.len()));
+    assert_eq!(Some(true), v.get("ok").and_then(Value::as_bool));
+    assert_eq!(None, v.get("none"));
+
+    let v: Value = " { \"a\" : [ -1 , 2e3, \"\\u00e9\" ] , \"b\": {} } ".parse().unwrap();
+    assert_eq!(r#"{"a":[-1,2000,"é"],"b":{}}"#, v.to_string());
+
+    for s in &["", "{", "[1,]", "{\"a\" 1}", "nul", "1 2", "\"abc"] {
+        assert!(s.parse::<Value>().is_err(), "{}", s);
+    }
+}
@@ -0,0 +1,118 @@
+/*!
+ * 既知の最良解
+ *
+ * problem/known.txt に全ての部屋の回転数、歩数、参照解を 1 行ずつ記録しておく:
+ *
+ * ```text
+ * # room bound rotate step solution
+ * 00-0 optimal 17 17 9 10 11 12 13 ...
+ * 03-2 upper 50 63 42 34 26 18 10 ...
+ * ```
+ *
+ * bound が optimal なら rotate は最短(IDA* で確認済み)で、step はその回転数の解の中で最小。
+ * upper なら探索が終わっていない部屋で、rotate と step は参照解のもの(最短とは限らない)。
+ * solution はこの rotate と step を満たす解の 1 つ。
+ *
+ * optimal の部屋は "egypt solve --algo ida --reduce --expand" で解き切ったもの。
+ * upper の部屋は IDA* の途中までの解、beam 探索や NMCS の解を optimize したもののうち最良のもの。
+ *
+ * upper の部屋は最短が分かっていない(未解決)。テストでは、ソルバーの解がこの上界を超えないことだけを確かめる。
+ * 解き切れたら optimal に書き換える。
+ */
+
+use std::fs;
+use std::path::{ Path };
+
+use failure;
+use itertools::{ Itertools };
+
+/// 記録ファイルの既定の場所
+pub const KNOWN_PATH: &str = "problem/known.txt";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Bound {
+    /// 回転数は最短
+    Optimal,
+    /// 回転数は上界
+    Upper,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Known {
+    pub room:     String,
+    pub bound:    Bound,
+    pub rotate:   u32,
+    pub step:     u32,
+    pub solution: Vec<u8>,
+}
+
+impl Known {
+    pub fn is_optimal(&self) -> bool {
+        self.bound == Bound::Optimal
+    }
+}
+
+/// 記録を解析する(空行と '#' で始まる行は無視)
+pub fn parse(s: &str) -> Result<Vec<Known>, failure::Error> {
+    let mut res = vec![];
+    for line in s.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+
+        let ws: Vec<_> = line.split_whitespace().collect();
+        if ws.len() < 4 {
+            bail!("known: too few fields: {}", line);
+        }
+        let bound = match ws[1] {
+            "optimal" => Bound::Optimal,
+            "upper"   => Bound::Upper,
+            _         => bail!("known: unknown bound: {}", line),
+        };
+        let solution: Vec<u8> = ws[4..].iter()
+            .map(|w| w.parse())
+            .collect::<Result<_,_>>()?;
+        let rotate = ws[2].parse()?;
+        if solution.len() as u32 != rotate {
+            bail!("known: rotate does not match the solution: {}", line);
+        }
+        res.push(Known {
+            room: ws[0].to_owned(),
+            bound,
+            rotate,
+            step: ws[3].parse()?,
+            solution,
+        });
+    }
+    Ok(res)
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<Known>, failure::Error> {
+    parse(&fs::read_to_string(path)?)
+}
+
+/// 1 部屋分の記録を 1 行にする
+pub fn format(known: &Known) -> String {
+    let bound = match known.bound {
+        Bound::Optimal => "optimal",
+        Bound::Upper   => "upper",
+    };
+    format!("{} {} {} {} {}", known.room, bound, known.rotate, known.step, known.solution.iter().join(" "))
+        .trim_end()
+        .to_owned()
+}
+
+#[test]
+fn test_known() {
+    let s = "# comment\n00-2 optimal 2 3 28 36\n\n03-2 upper 1 40 9\n00-9 optimal 0 0\n";
+    let knowns = parse(s).unwrap();
+    assert_eq!(3, knowns.len());
+    assert_eq!(Known { room: "00-2".to_owned(), bound: Bound::Optimal, rotate: 2, step: 3, solution: vec![28, 36] }, knowns[0]);
+    assert!(!knowns[1].is_optimal());
+    assert!(knowns[2].solution.is_empty());
+    assert_eq!("00-2 optimal 2 3 28 36", format(&knowns[0]));
+    assert_eq!("00-9 optimal 0 0", format(&knowns[2]));
+
+    assert!(parse("00-2 optimal 3 3 28 36").is_err());
+    assert!(parse("00-2 best 2 3 28 36").is_err());
+    assert!(parse("00-2 optimal 2").is_err());
+}
@@ -1,34 +1,210 @@
+#![allow(non_local_definitions)]  // failure_derive が生成する impl に対する警告
+
 #[macro_use] extern crate failure;
 extern crate generic_array;
 extern crate itertools;
+extern crate permutohedron;
 extern crate rand;
 
+use std::fmt;
+use std::io;
+use std::sync::{ mpsc, Arc, atomic::{ AtomicBool, Ordering } };
+use std::thread;
+
+use itertools::{ Itertools };
+
 pub mod board;
 pub mod util;
 
+pub mod algo;
+pub mod anneal;
+pub mod batch;
+pub mod beam;
 pub mod bfs;
+pub mod bfs_disk;
+pub mod bnb;
+pub mod cache;
+pub mod checkpoint;
+pub mod cli;
+pub mod ffi;
 pub mod genetic;
 pub mod ida;
+pub mod island;
 pub mod iddfs;
+pub mod json;
+pub mod known;
+pub mod nmcs;
+pub mod population;
+pub mod report;
+pub mod roomorder;
+pub mod server;
 
 pub trait Solver {
     fn solve(&mut self, board: &board::Board) -> Result<Vec<Vec<u8>>,SolverError>;
+
+    /// 直前の solve で調べたノード数。数えていないソルバーは None
+    fn node_count(&self) -> Option<u64> {
+        None
+    }
+
+    /// 直前の solve の深さ(反復)ごとの統計。記録しないソルバーは空
+    fn depth_stats(&self) -> Vec<DepthStats> {
+        vec![]
+    }
+
+    /// 直前の solve の解をキャッシュから返したなら、その記録
+    fn cache_hit(&self) -> Option<&cache::Entry> {
+        None
+    }
+}
+
+/// 探索の 1 つの深さ(反復)の統計
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthStats {
+    pub depth: u32,
+    pub nodes: u64,
+    /// 秒
+    pub time:  f64,
+}
+
+/// 探索の中断要求
+///
+/// clone したものは同じ要求を共有する。別スレッドから cancel() すると、これを渡された
+/// ソルバーはなるべく早く探索をやめ、それまでに見つけた解を SolverError(kind() は Cancelled)として返す。
+#[derive(Debug, Clone, Default)]
+pub struct Cancel {
+    flag: Arc<AtomicBool>,
+}
+
+impl Cancel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+/// 解を見つけ次第その場で返せるソルバー
+pub trait StreamSolver {
+    /// 解を見つけるたびに f(depth, sol) を呼ぶ。
+    /// f が false を返したらそこで探索を打ち切り、Ok(()) を返す。
+    fn solve_stream(&mut self, board: &board::Board, f: &mut dyn FnMut(u32, &[u8]) -> bool) -> Result<(),SolverError>;
+}
+
+// StreamSolver の解を全て集めて Solver::solve() の結果とする
+// 従来通り、見つけた解は逐次 stderr にも出力する
+fn collect_solutions<S: StreamSolver + ?Sized>(solver: &mut S, board: &board::Board) -> Result<Vec<Vec<u8>>,SolverError> {
+    let mut sols = vec![];
+    let r = solver.solve_stream(board, &mut |_, sol| {
+        eprintln!("{}", sol.iter().join(" "));
+        sols.push(sol.to_vec());
+        true
+    });
+    match r {
+        Ok(())  => Ok(sols),
+        Err(e)  => Err(SolverError { sols, kind: e.kind }),
+    }
+}
+
+/// 解を (深さ, 解) の形で 1 つずつ返す遅延イテレータ
+///
+/// ソルバーは別スレッドで動き、次の解が要求されるまで探索を止めて待つ。
+/// イテレータを途中で捨てればそこで探索も打ち切られる。
+pub struct Solutions {
+    rx:     mpsc::Receiver<(u32,Vec<u8>)>,
+    handle: thread::JoinHandle<Result<(),SolverError>>,
+}
+
+impl Solutions {
+    pub fn new<S>(mut solver: S, board: &board::Board) -> Self
+        where S: StreamSolver + Send + 'static
+    {
+        let (tx, rx) = mpsc::sync_channel(0);
+        let board = board.clone();
+        let handle = thread::spawn(move || {
+            solver.solve_stream(&board, &mut |depth, sol| tx.send((depth, sol.to_vec())).is_ok())
+        });
+
+        Self {
+            rx,
+            handle,
+        }
+    }
+
+    /// 探索を打ち切り、その結果を返す(ノード数上限に達していれば Err)
+    pub fn finish(self) -> Result<(),SolverError> {
+        let Solutions { rx, handle } = self;
+        drop(rx);
+        handle.join().expect("solver thread panicked")
+    }
+}
+
+impl Iterator for Solutions {
+    type Item = (u32,Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
+
+/// ソルバーが解き終えられなかった理由
+#[derive(Debug, Clone, PartialEq)]
+pub enum SolverErrorKind {
+    /// ノード数などの上限に達した
+    Limit,
+    /// Cancel で打ち切られた
+    Cancelled,
+    /// 作業用ファイルの読み書きに失敗した
+    Io(String),
 }
 
 #[derive(Fail, Debug)]
-#[fail(display = "SolverError: solutions: {:?}", sols)]
 pub struct SolverError {
     sols: Vec<Vec<u8>>,
+    kind: SolverErrorKind,
 }
 
 impl SolverError {
     fn new(sols: Vec<Vec<u8>>) -> Self {
         Self {
             sols,
+            kind: SolverErrorKind::Limit,
+        }
+    }
+    fn cancelled(sols: Vec<Vec<u8>>) -> Self {
+        Self {
+            sols,
+            kind: SolverErrorKind::Cancelled,
+        }
+    }
+    fn io(e: &io::Error, sols: Vec<Vec<u8>>) -> Self {
+        Self {
+            sols,
+            kind: SolverErrorKind::Io(e.to_string()),
         }
     }
     #[allow(dead_code)]
     pub fn solutions(&self) -> &[Vec<u8>] {
         &self.sols
     }
+    pub fn kind(&self) -> &SolverErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for SolverError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            SolverErrorKind::Limit     => write!(f, "too many nodes")?,
+            SolverErrorKind::Cancelled => write!(f, "cancelled")?,
+            SolverErrorKind::Io(ref e) => write!(f, "I/O error: {}", e)?,
+        }
+        write!(f, " (solutions found so far: {})", self.sols.len())
+    }
 }
@@ -0,0 +1,530 @@
+extern crate failure;
+extern crate itertools;
+
+extern crate egypt;
+
+use std::env;
+use std::fs;
+use std::io::{ self, prelude::* };
+use std::process;
+use std::str::{ FromStr };
+use std::time;
+
+use itertools::{ Itertools };
+
+use egypt::board::{ Board };
+use egypt::util;
+
+use egypt::{ Cancel, SolverErrorKind };
+
+use egypt::algo;
+use egypt::batch::{ self, Format, Status };
+use egypt::cache::{ self, Cache };
+use egypt::checkpoint::{ Checkpoint };
+use egypt::cli::{ self, Args, OptSpec };
+use egypt::json::{ Value };
+use egypt::report;
+use egypt::roomorder::{ self, Problem };
+use egypt::server;
+
+const USAGE: &str = "\
+Usage: egypt <command> [options] [args]
+
+Commands:
+    solve       solve a room
+    batch       solve many rooms and print a summary
+    optimize    shorten known solutions
+    verify      check solutions against a room
+    show        print a room and its state
+    roomorder   find the shortest order to visit the rooms
+    cache       list, export or invalidate cached solutions
+    server      answer JSON requests on stdin, one per line
+    help        show help for a command
+
+Rooms and files are read from stdin when omitted or given as '-'.
+Run 'egypt help <command>' for the options of each command.
+";
+
+const SOLVE_USAGE: &str = "\
+Usage: egypt solve [--algo <name>] [--format <kind>] [options] [problem]
+
+Solve the room and print each solution as
+    <moves> # rotate=<n> step=<n>
+With --format json, print one JSON object with the algorithm, the board, the
+termination (solved, unsolved, limit, cancelled or error, with the message in
+'error'), the solutions (moves as [x, y]), the node count, the time and the
+per-depth search statistics. Exits with status 1 unless solved or unsolved.
+";
+
+const BATCH_USAGE: &str = "\
+Usage: egypt batch [--format <kind>] [--output <file>] [--algo <name>] [options] [problems...]
+
+Solve each room with the same solver options (the limits apply per room) and
+print a table of room, status, rotate, step, nodes, time and the number of
+solutions. Problems default to 'problem/*.in'; patterns containing '*' or '?'
+are expanded. Status is one of solved, unsolved, limit, cancelled or error.
+Exits with status 1 unless every room is solved.
+";
+
+const OPTIMIZE_USAGE: &str = "\
+Usage: egypt optimize [--format <kind>] <solutions> [problem]
+
+Remove redundant moves from each solution in the file and print the results.
+With --format json, print one JSON object with the board, the termination, the
+optimized solutions and the invalid solutions that were ignored.
+";
+
+const VERIFY_USAGE: &str = "\
+Usage: egypt verify [--format <kind>] <solutions> [problem]
+
+Check each solution in the file. Prints 'ok' or 'NG' per solution and
+exits with status 1 if any solution is wrong. With --format json, print one
+JSON object with the board and the result of each solution.
+";
+
+const FORMAT_OPTIONS: &[OptSpec] = &[
+    OptSpec { name: "format", value: Some("kind"), help: "text|json (default: text)", algos: &[] },
+];
+
+const SHOW_USAGE: &str = "\
+Usage: egypt show [--moves <moves>] [problem]
+
+Print the room, the player position, the legal moves, the remaining pieces and
+the lower bound on the number of rotations. With --moves, apply the moves
+(space-separated cell indices) one at a time and print each state.
+";
+
+const SHOW_OPTIONS: &[OptSpec] = &[
+    OptSpec { name: "moves", value: Some("moves"), help: "moves to apply", algos: &[] },
+];
+
+const CACHE_USAGE: &str = "\
+Usage: egypt cache list
+       egypt cache export [--output <file>]
+       egypt cache invalidate [problems...]
+       egypt cache clear
+
+solve returns cached solutions for a room it has solved before (--no-cache
+skips the cache); batch and server use the cache only with --cache or
+--cache-dir. An entry is used only if it satisfies the requested --max-depth
+and --max-nodes. Only exhaustive searches (bfs, iddfs, ida and bnb without
+--max-depth-ini, and without --reduce unless --expand) are recorded.
+
+    list        print hash, rotate, step, solutions, solver and limits per entry
+    export      print every entry with its solutions and board in text form
+    invalidate  forget the given rooms (stdin when omitted)
+    clear       forget everything
+";
+
+const CACHE_OPTIONS: &[OptSpec] = &[
+    OptSpec { name: "cache-dir", value: Some("dir"),  help: "cache directory (default: $EGYPT_CACHE_DIR or ~/.cache/egypt)", algos: &[] },
+    OptSpec { name: "output",    value: Some("file"), help: "write the export to file instead of stdout", algos: &[] },
+];
+
+const SERVER_USAGE: &str = "\
+Usage: egypt server
+
+Read one JSON request per line from stdin and write one JSON response per line
+to stdout. A request is {\"id\": ..., \"method\": ..., ...} with method one of
+moves, apply, verify, solve, hint or cancel; the response is
+{\"id\": ..., \"result\": ...} or {\"id\": ..., \"error\": ...}.
+
+    moves   legal moves and state of \"board\"
+    apply   the board after playing \"moves\"
+    verify  whether \"moves\" solve the board
+    solve   solve with \"options\" (solve option names and values), as solve --format json
+    hint    solve, then give the first move of the best solution
+    cancel  stop the running solve or hint whose id is \"target\"
+
+Boards are the text format as a string, or {\"pos\": [x, y], \"rows\": [...]}
+where each row is a string or an array of cells. Moves are [x, y] or cell
+indices. solve and hint run in the background, so their responses may arrive
+out of order; a cancelled solve reports termination \"cancelled\".
+";
+
+const ROOMORDER_USAGE: &str = "\
+Usage: egypt roomorder [map]
+
+Read a 7x5 map with the start 's' and rooms '0'..'5' and print the orders
+that visit all rooms and return to the start in the fewest steps.
+";
+
+fn command_usage(command: &str) -> Option<String> {
+    let usage = match command {
+        "solve"     => format!("{}\nOptions:\n{}{}", SOLVE_USAGE,
+                               cli::format_options(FORMAT_OPTIONS), cli::format_options(algo::SOLVE_OPTIONS)),
+        "batch"     => format!("{}\nOptions:\n{}{}", BATCH_USAGE,
+                               cli::format_options(batch::BATCH_OPTIONS), cli::format_options(algo::SOLVE_OPTIONS)),
+        "optimize"  => format!("{}\nOptions:\n{}", OPTIMIZE_USAGE, cli::format_options(FORMAT_OPTIONS)),
+        "verify"    => format!("{}\nOptions:\n{}", VERIFY_USAGE, cli::format_options(FORMAT_OPTIONS)),
+        "show"      => format!("{}\nOptions:\n{}", SHOW_USAGE, cli::format_options(SHOW_OPTIONS)),
+        "roomorder" => ROOMORDER_USAGE.to_owned(),
+        "server"    => SERVER_USAGE.to_owned(),
+        "cache"     => format!("{}\nOptions:\n{}", CACHE_USAGE, cli::format_options(CACHE_OPTIONS)),
+        _           => return None,
+    };
+    Some(usage)
+}
+
+fn print_solutions(board: &Board, sols: &[Vec<u8>]) {
+    let sols = util::solutions_with_step(board, sols);
+    for (sol, step) in sols {
+        println!("{} # rotate={} step={}",
+                 sol.iter().join(" "),
+                 sol.len(), step);
+        assert!(util::verify_solution(board, &sol));
+    }
+}
+
+/// --format の値。solve, optimize, verify は text と json のみ
+fn output_format(args: &Args) -> Result<Format, failure::Error> {
+    match args.get_or("format", Format::Text)? {
+        Format::Csv => Err(failure::err_msg("--format csv is only supported by batch")),
+        format      => Ok(format),
+    }
+}
+
+fn read_board(path: Option<&String>) -> Result<Board, failure::Error> {
+    let s = cli::read_input(path.map(|s| s.as_str()))?;
+    Board::from_str(&s)
+}
+
+fn cmd_solve(args: &Args) -> Result<i32, failure::Error> {
+    if args.positional.len() > 1 { return Ok(usage_error("solve")); }
+
+    // 再開時は局面もチェックポイントから読む
+    if args.value("resume").is_some() && !args.positional.is_empty() {
+        return Err(failure::err_msg("--resume reads the board from the checkpoint; do not give a problem file"));
+    }
+
+    let format = output_format(args)?;
+    let mut solver = algo::build_solver_with(args, &Cancel::new(), true)?;
+    let board = match args.value("resume") {
+        Some(path) => Checkpoint::load(path)?.board,
+        None       => read_board(args.positional.first())?,
+    };
+
+    let t = time::Instant::now();
+    let r = solver.solve(&board);
+    let time = util::duration_float(&t.elapsed());
+    let (sols, status) = match r {
+        Ok(sols) => {
+            let status = if sols.is_empty() { Status::Unsolved } else { Status::Solved };
+            (sols, status)
+        },
+        Err(e) => {
+            eprintln!("{}", e);
+            let status = match *e.kind() {
+                SolverErrorKind::Limit     => Status::Limit,
+                SolverErrorKind::Cancelled => Status::Cancelled,
+                SolverErrorKind::Io(_)     => Status::Error(e.to_string()),
+            };
+            (e.solutions().to_vec(), status)
+        }
+    };
+
+    if format == Format::Json {
+        let mut v = report::solve_json(algo::algo_name(args)?, &board, status.as_str(), &sols, &*solver, time);
+        if let Status::Error(ref msg) = status {
+            v = v.with("error", msg.as_str());
+        }
+        println!("{}", v);
+    }
+    else {
+        print_solutions(&board, &sols);
+    }
+    Ok(match status {
+        Status::Solved | Status::Unsolved => 0,
+        _                                 => 1,
+    })
+}
+
+fn cmd_batch(args: &Args) -> Result<i32, failure::Error> {
+    if args.value("checkpoint").is_some() || args.value("resume").is_some() {
+        return Err(failure::err_msg("--checkpoint and --resume cannot be used with batch"));
+    }
+    let format: Format = args.get_or("format", Format::Text)?;
+    let paths = if args.positional.is_empty() {
+        batch::expand_patterns(&[batch::PATTERN_DEFAULT])?
+    }
+    else {
+        batch::expand_patterns(&args.positional)?
+    };
+    // 部屋を解き始める前にオプションの誤りを報告する
+    algo::build_solver(args)?;
+
+    let mut results = vec![];
+    for (i, path) in paths.iter().enumerate() {
+        let mut solver = algo::build_solver(args)?;
+        let res = batch::solve_file(path, &mut *solver);
+        match res.status {
+            Status::Error(ref msg) => eprintln!("[{}/{}] {}: error: {}", i+1, paths.len(), res.room, msg),
+            _ => eprintln!("[{}/{}] {}: {} rotate={} step={} time={:.3}",
+                           i+1, paths.len(), res.room, res.status.as_str(),
+                           res.rotate.map_or("-".to_owned(), |x| x.to_string()),
+                           res.step.map_or("-".to_owned(), |x| x.to_string()),
+                           res.time),
+        }
+        results.push(res);
+    }
+
+    match args.value("output") {
+        Some(path) => {
+            let mut w = io::BufWriter::new(fs::File::create(path)?);
+            batch::write_report(&mut w, &results, format)?;
+            w.flush()?;
+        },
+        None => batch::write_report(&mut io::stdout().lock(), &results, format)?,
+    }
+    let all_solved = results.iter().all(|res| res.status == Status::Solved);
+    Ok(if all_solved { 0 } else { 1 })
+}
+
+fn cmd_optimize(args: &Args) -> Result<i32, failure::Error> {
+    if args.positional.is_empty() || args.positional.len() > 2 { return Ok(usage_error("optimize")); }
+    let format = output_format(args)?;
+    let sols = util::parse_solutions(&cli::read_input(Some(&args.positional[0]))?)?;
+    let board = read_board(args.positional.get(1))?;
+
+    let mut optimized = vec![];
+    let mut invalid = vec![];
+    for sol in sols {
+        if !util::verify_solution(&board, &sol) {
+            eprintln!("invalid solution ignored: {}", sol.iter().join(" "));
+            invalid.push(report::moves_json(&sol));
+            continue;
+        }
+        optimized.append(&mut util::optimize_solution(&board, &sol));
+    }
+    optimized.sort();
+    optimized.dedup();
+
+    if format == Format::Json {
+        let status = if optimized.is_empty() { Status::Unsolved } else { Status::Solved };
+        let value = Value::object()
+            .with("board",       report::board_json(&board))
+            .with("termination", status.as_str())
+            .with("solutions",   report::solutions_json(&board, &optimized))
+            .with("invalid",     invalid);
+        println!("{}", value);
+    }
+    else {
+        print_solutions(&board, &optimized);
+    }
+    Ok(0)
+}
+
+fn cmd_verify(args: &Args) -> Result<i32, failure::Error> {
+    if args.positional.is_empty() || args.positional.len() > 2 { return Ok(usage_error("verify")); }
+    let format = output_format(args)?;
+    let sols = util::parse_solutions(&cli::read_input(Some(&args.positional[0]))?)?;
+    let board = read_board(args.positional.get(1))?;
+
+    let mut status = 0;
+    let mut results = vec![];
+    for sol in sols {
+        let valid = util::verify_solution(&board, &sol);
+        let step = if valid { Some(util::step_of_solution(&board, &sol)) } else { None };
+        if format == Format::Json {
+            results.push(Value::object()
+                .with("moves",  report::moves_json(&sol))
+                .with("valid",  valid)
+                .with("rotate", sol.len())
+                .with("step",   step));
+        }
+        else if let Some(step) = step {
+            println!("ok {} # rotate={} step={}", sol.iter().join(" "), sol.len(), step);
+        }
+        else {
+            println!("NG {}", sol.iter().join(" "));
+        }
+        if !valid { status = 1; }
+    }
+    if format == Format::Json {
+        let value = Value::object()
+            .with("board",   report::board_json(&board))
+            .with("results", results);
+        println!("{}", value);
+    }
+    Ok(status)
+}
+
+fn print_state(board: &Board) {
+    print!("{}", board);
+    let (x, y) = Board::idx2xy(board.pos);
+    println!("pos: {} ({},{})", board.pos, x, y);
+    println!("moves: {}", board.moves().iter().join(" "));
+    println!("pieces: {}", board.counts().iter().join(" "));
+    if board.is_solved() {
+        println!("state: solved");
+    }
+    else if board.is_stuck() || board.moves().is_empty() {
+        println!("state: stuck");
+    }
+    else {
+        println!("state: playing (least_to_solve={})", board.least_to_solve());
+    }
+}
+
+fn cmd_show(args: &Args) -> Result<i32, failure::Error> {
+    if args.positional.len() > 1 { return Ok(usage_error("show")); }
+    let mut board = read_board(args.positional.first())?;
+    print_state(&board);
+
+    if let Some(moves) = args.value("moves") {
+        for to in util::parse_solution(moves)? {
+            if !board.moves().contains(&to) {
+                println!();
+                println!("illegal move: {}", to);
+                return Ok(1);
+            }
+            board.move_(to);
+            println!();
+            println!("move {}", to);
+            print_state(&board);
+        }
+    }
+    Ok(0)
+}
+
+fn cmd_roomorder(args: &Args) -> Result<i32, failure::Error> {
+    if args.positional.len() > 1 { return Ok(usage_error("roomorder")); }
+    let s = cli::read_input(args.positional.first().map(|s| s.as_str()))?;
+    let problem = Problem::from_str(&s)?;
+
+    let (orders, step) = roomorder::solve(&problem);
+    println!("step={}", step);
+    for order in orders {
+        println!("{:?}", order);
+    }
+    Ok(0)
+}
+
+fn cmd_server(args: &Args) -> Result<i32, failure::Error> {
+    if !args.positional.is_empty() { return Ok(usage_error("server")); }
+    let stdin = io::stdin();
+    server::serve(stdin.lock(), io::stdout())?;
+    Ok(0)
+}
+
+fn cmd_cache(args: &Args) -> Result<i32, failure::Error> {
+    let dir = algo::cache_dir(args, true)
+        .ok_or_else(|| failure::err_msg("no cache directory (set EGYPT_CACHE_DIR or HOME)"))?;
+    let cache = Cache::new(dir);
+
+    let action = match args.positional.first() {
+        Some(action) => action.as_str(),
+        None         => return Ok(usage_error("cache")),
+    };
+    let rest = &args.positional[1..];
+    if action != "invalidate" && !rest.is_empty() { return Ok(usage_error("cache")); }
+    if action != "export" && args.value("output").is_some() { return Ok(usage_error("cache")); }
+
+    match action {
+        "list" => {
+            for entry in cache.entries()? {
+                let best = util::solutions_with_step(&entry.board, &entry.solutions).into_iter().next();
+                println!("{:016x} rotate={} step={} solutions={} solver={} limits={}",
+                         cache::board_hash(&entry.board),
+                         best.as_ref().map_or(0, |(sol, _)| sol.len()),
+                         best.as_ref().map_or(0, |&(_, step)| step),
+                         entry.solutions.len(), entry.solver, entry.limits);
+            }
+        },
+        "export" => {
+            let mut w: Box<dyn Write> = match args.value("output") {
+                Some(path) => Box::new(io::BufWriter::new(fs::File::create(path)?)),
+                None       => Box::new(io::stdout()),
+            };
+            for (i, entry) in cache.entries()?.iter().enumerate() {
+                if i > 0 { writeln!(w)?; }
+                writeln!(w, "# {:016x}", cache::board_hash(&entry.board))?;
+                entry.write(&mut w)?;
+            }
+            w.flush()?;
+        },
+        "invalidate" => {
+            let paths: Vec<Option<&String>> = if rest.is_empty() { vec![None] } else { rest.iter().map(Some).collect() };
+            for path in paths {
+                let board = read_board(path)?;
+                let name = path.map_or("-", |s| s.as_str());
+                if cache.remove(&board)? {
+                    eprintln!("invalidated: {}", name);
+                }
+                else {
+                    eprintln!("not cached: {}", name);
+                }
+            }
+        },
+        "clear" => {
+            eprintln!("removed {} entries", cache.clear()?);
+        },
+        _ => return Ok(usage_error("cache")),
+    }
+    Ok(0)
+}
+
+fn usage_error(command: &str) -> i32 {
+    eprintln!("{}", command_usage(command).unwrap_or_else(|| USAGE.to_owned()));
+    2
+}
+
+fn run(argv: &[String]) -> Result<i32, failure::Error> {
+    let command = match argv.first() {
+        Some(command) => command.as_str(),
+        None          => { eprint!("{}", USAGE); return Ok(2); },
+    };
+    let rest = &argv[1..];
+
+    let spec: Vec<OptSpec> = match command {
+        "solve"                             => [FORMAT_OPTIONS, algo::SOLVE_OPTIONS].concat(),
+        "batch"                             => [batch::BATCH_OPTIONS, algo::SOLVE_OPTIONS].concat(),
+        "optimize" | "verify"               => FORMAT_OPTIONS.to_vec(),
+        "show"                              => SHOW_OPTIONS.to_vec(),
+        "cache"                             => CACHE_OPTIONS.to_vec(),
+        "roomorder" | "server"              => vec![],
+        "help" | "-h" | "--help"            => {
+            match rest.first() {
+                Some(command) => match command_usage(command) {
+                    Some(usage) => print!("{}", usage),
+                    None        => { eprintln!("unknown command: {}", command); return Ok(2); },
+                },
+                None => print!("{}", USAGE),
+            }
+            return Ok(0);
+        },
+        _ => {
+            eprintln!("unknown command: {}\n", command);
+            eprint!("{}", USAGE);
+            return Ok(2);
+        },
+    };
+
+    let args = Args::parse(rest, &spec)?;
+    if args.flag("help") {
+        print!("{}", command_usage(command).unwrap());
+        return Ok(0);
+    }
+    match command {
+        "solve"     => cmd_solve(&args),
+        "batch"     => cmd_batch(&args),
+        "optimize"  => cmd_optimize(&args),
+        "verify"    => cmd_verify(&args),
+        "show"      => cmd_show(&args),
+        "roomorder" => cmd_roomorder(&args),
+        "cache"     => cmd_cache(&args),
+        "server"    => cmd_server(&args),
+        _           => unreachable!(),
+    }
+}
+
+fn main() {
+    let argv: Vec<String> = env::args().skip(1).collect();
+    match run(&argv) {
+        Ok(status) => process::exit(status),
+        Err(e)     => {
+            eprintln!("egypt: {}", e);
+            process::exit(1);
+        },
+    }
+}
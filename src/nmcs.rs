@@ -0,0 +1,145 @@
+/*!
+ * 入れ子モンテカルロ探索(Nested Monte Carlo Search)
+ *
+ * レベル 0 はランダムプレイアウト。レベル n では各手についてレベル n-1 の探索を行い、
+ * これまでで最良の手順に沿って 1 手進めることを終局まで繰り返す。
+ * これを iterations 回(毎回異なる乱数列で)行い、見つけた解のうち最短のものを返す。
+ */
+
+use std::cmp;
+
+use rand::{ prelude::*, prng::{ XorShiftRng } };
+
+use ::{ Cancel, Solver, SolverError };
+use board::{ Board };
+use util;
+
+pub struct NmcsSolver {
+    level:      u32,
+    iterations: u32,
+    max_len:    usize,
+    seed:       u64,
+    rng:        XorShiftRng,
+    cancel:     Cancel,
+}
+
+impl NmcsSolver {
+    const MAX_LEN_DEFAULT: usize = 100;
+
+    pub fn new(level: u32, iterations: u32) -> Self {
+        let seed = thread_rng().gen();
+        Self {
+            level,
+            iterations,
+            max_len: NmcsSolver::MAX_LEN_DEFAULT,
+            seed,
+            rng:     util::seeded_rng(seed),
+            cancel:  Cancel::new(),
+        }
+    }
+
+    /// 乱数のシードを指定する(デフォルトはランダム)
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// 1 つの手順の最大手数を指定する(デフォルトは 100)
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// cancel が要求されたら次の反復に進む前に探索を打ち切り、それまでの解を SolverError として返す
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// 使っているシード
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn is_terminal(&self, board: &Board, len: usize) -> bool {
+        board.is_solved() || board.is_stuck() || len >= self.max_len || board.moves().is_empty()
+    }
+
+    // 終局の評価値。解けていれば手数が短いほど、解けていなければピースが少ないほど高い
+    fn evaluate(board: &Board, len: usize) -> i64 {
+        if board.is_solved() {
+            return 1000 - len as i64;
+        }
+        let pieces: i64 = board.counts().iter()
+            .fold(0, |sum,&e| sum + i64::from(e));
+        -pieces
+    }
+
+    fn playout(&mut self, board: &Board, sol: &[u8]) -> (i64, Vec<u8>) {
+        let mut board = board.clone();
+        let mut sol = sol.to_vec();
+        while !self.is_terminal(&board, sol.len()) {
+            let moves = board.moves();
+            let to = moves[self.rng.gen_range(0, moves.len())];
+            board.move_(to);
+            sol.push(to);
+        }
+        (NmcsSolver::evaluate(&board, sol.len()), sol)
+    }
+
+    // sol まで進めた局面 board からレベル level の探索を行い、(評価値, 初期局面からの手順) を返す
+    fn nested(&mut self, board: &Board, level: u32, sol: &[u8]) -> (i64, Vec<u8>) {
+        if level == 0 { return self.playout(board, sol); }
+
+        let mut board = board.clone();
+        let mut sol = sol.to_vec();
+        let mut best = (i64::MIN, vec![]);
+        while !self.is_terminal(&board, sol.len()) {
+            for to in board.moves() {
+                let mut board2 = board.clone();
+                board2.move_(to);
+                sol.push(to);
+                let res = self.nested(&board2, level-1, &sol);
+                sol.pop();
+                if res.0 > best.0 { best = res; }
+            }
+
+            let to = best.1[sol.len()];
+            board.move_(to);
+            sol.push(to);
+        }
+
+        if best.0 == i64::MIN {
+            (NmcsSolver::evaluate(&board, sol.len()), sol)
+        }
+        else {
+            best
+        }
+    }
+}
+
+impl Solver for NmcsSolver {
+    fn solve(&mut self, board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
+        self.rng = util::seeded_rng(self.seed);
+
+        let mut res: Vec<Vec<u8>> = vec![];
+        for i in 0..self.iterations {
+            if self.cancel.is_cancelled() { return Err(SolverError::cancelled(res)); }
+            let (_, sol) = self.nested(board, self.level, &[]);
+            let solved = util::verify_solution(board, &sol);
+            eprintln!("Iteration {}: len={} solved={}", i, sol.len(), solved);
+            if !solved { continue; }
+
+            let best_len = res.first().map_or(usize::MAX, |e| e.len());
+            match sol.len().cmp(&best_len) {
+                cmp::Ordering::Less    => { res = vec![sol]; },
+                cmp::Ordering::Equal   => { res.push(sol); },
+                cmp::Ordering::Greater => {},
+            }
+        }
+
+        res.sort();
+        res.dedup();
+        Ok(res)
+    }
+}
@@ -0,0 +1,126 @@
+/*!
+ * 遺伝的アルゴリズムの集団
+ *
+ * テキスト形式で保存する:
+ *
+ * ```text
+ * encoding relative
+ * max_len 30
+ * gene 3 17 200 ...
+ * board
+ * <Board の Display 形式>
+ * ```
+ *
+ * gene は個体の遺伝子で、評価の良い順に並ぶ。
+ * encoding は遺伝子の解釈(relative または absolute)。
+ */
+
+use std::fs;
+use std::io::{ self, prelude::* };
+use std::path::{ Path };
+
+use failure;
+use itertools::{ Itertools };
+
+use board::{ Board };
+use genetic::{ Encoding };
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Population {
+    pub board:    Board,
+    pub encoding: Encoding,
+    pub max_len:  u32,
+    pub genes:    Vec<Vec<u8>>,
+}
+
+impl Population {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, failure::Error> {
+        let s = fs::read_to_string(path)?;
+
+        let mut encoding = Encoding::Relative;
+        let mut max_len  = None;
+        let mut genes    = vec![];
+        let mut lines = s.lines();
+        while let Some(line) = lines.next() {
+            let (key, rest) = match line.find(' ') {
+                Some(i) => (&line[..i], &line[i+1..]),
+                None    => (line, ""),
+            };
+            match key {
+                "encoding" => {
+                    encoding = match rest.trim() {
+                        "relative" => Encoding::Relative,
+                        "absolute" => Encoding::Absolute,
+                        _          => bail!("population: unknown encoding: {}", rest),
+                    };
+                },
+                "max_len"  => { max_len = Some(rest.trim().parse()?); },
+                "gene"     => {
+                    let gene = rest.split_whitespace()
+                        .map(|w| w.parse())
+                        .collect::<Result<_,_>>()?;
+                    genes.push(gene);
+                },
+                "board"    => {
+                    let board = lines.join("\n").parse()?;
+                    let max_len = max_len.ok_or_else(|| format_err!("population: max_len missing"))?;
+                    return Ok(Self {
+                        board,
+                        encoding,
+                        max_len,
+                        genes,
+                    });
+                },
+                ""         => {},
+                _          => bail!("population: unexpected line: {}", line),
+            }
+        }
+        bail!("population: board missing")
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut w = io::BufWriter::new(fs::File::create(path)?);
+        let encoding = match self.encoding {
+            Encoding::Relative => "relative",
+            Encoding::Absolute => "absolute",
+        };
+        writeln!(w, "encoding {}", encoding)?;
+        writeln!(w, "max_len {}", self.max_len)?;
+        for gene in &self.genes {
+            writeln!(w, "gene {}", gene.iter().join(" "))?;
+        }
+        writeln!(w, "board")?;
+        write!(w, "{}", self.board)?;
+        w.flush()
+    }
+}
+
+#[test]
+fn test_population() {
+    use std::env;
+    use std::process;
+
+    let board: Board = "\
+1 1
+#######4
+#010...#
+#.<1.^2#
+#.2.1..#
+3......#
+#.v..>.3
+#......v
+#######4
+".parse().unwrap();
+    let pop = Population {
+        board,
+        encoding: Encoding::Absolute,
+        max_len:  3,
+        genes:    vec![vec![18, 21, 255], vec![0, 1, 2]],
+    };
+
+    let path = env::temp_dir().join(format!("egypt-test-population-{}", process::id()));
+    pop.save(&path).unwrap();
+    let pop2 = Population::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(pop, pop2);
+}
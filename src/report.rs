@@ -0,0 +1,89 @@
+/*!
+ * 局面・解・探索統計の JSON 表現
+ *
+ * 局面は {"pos": [x, y], "rows": ["#######4", ...]} の形で、rows は Board の Display 形式の各行。
+ * 解の手は (x, y) の組の列で表す。
+ */
+
+use ::{ DepthStats, Solver };
+use board::{ Board };
+use json::{ Value };
+use util;
+
+/// 手(セル)を [x, y] にする
+pub fn move_json(pos: u8) -> Value {
+    let (x, y) = Board::idx2xy(pos);
+    Value::from(vec![x, y])
+}
+
+/// 手順を (x, y) の列にする
+pub fn moves_json(sol: &[u8]) -> Value {
+    Value::Array(sol.iter().map(|&pos| move_json(pos)).collect())
+}
+
+pub fn board_json(board: &Board) -> Value {
+    let s = board.to_string();
+    let rows: Vec<_> = s.lines().skip(1).collect();
+    Value::object()
+        .with("pos",  move_json(board.pos))
+        .with("rows", rows)
+}
+
+/// 解の列。回転数、歩数の少ない順に並べる(解は正しいものとする)
+pub fn solutions_json(board: &Board, sols: &[Vec<u8>]) -> Value {
+    let sols = util::solutions_with_step(board, sols);
+    Value::Array(sols.into_iter()
+        .map(|(sol, step)| {
+            Value::object()
+                .with("moves",  moves_json(&sol))
+                .with("rotate", sol.len())
+                .with("step",   step)
+        })
+        .collect())
+}
+
+pub fn depth_stats_json(stats: &[DepthStats]) -> Value {
+    Value::Array(stats.iter()
+        .map(|st| {
+            Value::object()
+                .with("depth", st.depth)
+                .with("nodes", st.nodes)
+                .with("time",  st.time)
+        })
+        .collect())
+}
+
+/// solve の結果。termination は終了理由("solved" など)、time は秒
+pub fn solve_json(algo: &str, board: &Board, termination: &str, sols: &[Vec<u8>], solver: &dyn Solver, time: f64) -> Value {
+    Value::object()
+        .with("algo",        algo)
+        .with("board",       board_json(board))
+        .with("termination", termination)
+        .with("solutions",   solutions_json(board, sols))
+        .with("nodes",       solver.node_count())
+        .with("time",        time)
+        .with("depths",      depth_stats_json(&solver.depth_stats()))
+        .with("cache",       solver.cache_hit().map(|entry| Value::object()
+                                 .with("solver", &entry.solver[..])
+                                 .with("limits", &entry.limits[..])))
+}
+
+#[test]
+fn test_report() {
+    let board: Board = "\
+1 1
+#######4
+#010...#
+#.<1.^2#
+#.2.1..#
+3......#
+#.v..>.3
+#......v
+#######4
+".parse().unwrap();
+    assert_eq!("{\"pos\":[1,1],\"rows\":[\"#######4\",\"#010...#\",\"#.<1.^2#\",\"#.2.1..#\",\"3......#\",\"#.v..>.3\",\"#......v\",\"#######4\"]}",
+               board_json(&board).to_string());
+    assert_eq!("[[2,2],[5,2]]", moves_json(&[18, 21]).to_string());
+    assert_eq!(r#"[{"depth":3,"nodes":10,"time":0.5}]"#,
+               depth_stats_json(&[DepthStats { depth: 3, nodes: 10, time: 0.5 }]).to_string());
+}
@@ -1,26 +1,30 @@
-#[macro_use] extern crate failure;
-extern crate permutohedron;
+/*!
+ * ピラミッド内の部屋を回る順番の最適化
+ *
+ * 7x5 のマップ上のスタート地点 's' と部屋 '0'..'5' を読み、全ての部屋を回って
+ * スタート地点に戻るまでの歩数(マンハッタン距離)が最小になる順番を全探索で求める。
+ */
 
 use std::char;
 use std::fmt;
-use std::io::{ self, prelude::* };
 use std::iter::{ FromIterator };
 use std::str::{ FromStr };
 
+use failure;
 use permutohedron::{ LexicalPermutation };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct Point {
-    x: i32,
-    y: i32,
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
 }
 
 impl Point {
-    fn new(x: i32, y: i32) -> Self {
+    pub fn new(x: i32, y: i32) -> Self {
         Self { x, y }
     }
 
-    fn distance(&self, other: &Point) -> i32 {
+    pub fn distance(&self, other: &Point) -> i32 {
         (self.x-other.x).abs() + (self.y-other.y).abs()
     }
 }
@@ -50,9 +54,9 @@ fn test_point() {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct Problem {
-    start: Point,
-    rooms: Vec<Point>,
+pub struct Problem {
+    pub start: Point,
+    pub rooms: Vec<Point>,
 }
 
 impl fmt::Display for Problem {
@@ -94,7 +98,7 @@ impl FromStr for Problem {
                     's' => {
                         start = Point::new(x, y);
                     },
-                    '0'...'5' => {
+                    '0'..='5' => {
                         rooms[c.to_digit(10).unwrap() as usize] = Point::new(x, y);
                     },
                     '.' => {},
@@ -123,7 +127,8 @@ fn test_problem() {
     assert_eq!(problem_str, format!("{}", problem));
 }
 
-fn calc_step(problem: &Problem, perm: &[usize]) -> i32 {
+/// start から perm の順に部屋を回って start に戻るまでの歩数
+pub fn calc_step(problem: &Problem, perm: &[usize]) -> i32 {
     let mut step = 0;
 
     let first = &problem.rooms[*perm.first().unwrap()];
@@ -141,7 +146,8 @@ fn calc_step(problem: &Problem, perm: &[usize]) -> i32 {
     step
 }
 
-fn solve(problem: &Problem) -> (Vec<Vec<usize>>, i32) {
+/// 歩数が最小になる部屋の順番を全て求める
+pub fn solve(problem: &Problem) -> (Vec<Vec<usize>>, i32) {
     let mut res = vec![];
     let mut step_min = 999999999;
 
@@ -158,18 +164,3 @@ fn solve(problem: &Problem) -> (Vec<Vec<usize>>, i32) {
 
     (res, step_min)
 }
-
-fn main() -> Result<(), failure::Error> {
-    let mut s = String::new();
-    io::stdin().read_to_string(&mut s)?;
-    let problem = Problem::from_str(&s)?;
-
-    let (sols, step) = solve(&problem);
-
-    println!("step={}", step);
-    for sol in sols {
-        println!("{:?}", sol);
-    }
-
-    Ok(())
-}
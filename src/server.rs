@@ -0,0 +1,362 @@
+/*!
+ * 行区切り JSON のサーバー
+ *
+ * 1 行に 1 つの要求を読み、1 行に 1 つの応答を書く。"egypt server" で標準入出力につなぐ:
+ *
+ * ```text
+ * {"id": 1, "method": "solve", "board": "3 4\n###26###\n...", "options": {"algo": "ida"}}
+ * {"id": 1, "result": {"algo": "ida", "board": {...}, "termination": "solved", ...}}
+ * {"id": 2, "method": "apply", "board": {"pos": [3, 4], "rows": [...]}, "moves": [[3, 3]]}
+ * {"id": 2, "error": "illegal move: [3,3]"}
+ * ```
+ *
+ * 局面はテキスト形式の文字列か、report::board_json() と同じオブジェクトで渡す。
+ * rows の各行は文字列でも 1 文字ずつの配列でもよい。手は [x, y] かセルの番号で渡す。
+ *
+ * - moves:  打てる手と状態(solved, stuck, playing)
+ * - apply:  moves を順に打った後の局面
+ * - verify: moves が解になっているか
+ * - solve:  options("egypt solve" のオプション名と値)のソルバーで解く
+ * - hint:   solve と同じく解き、最良の解の最初の手を返す
+ * - cancel: 実行中の solve, hint(id が target のもの)を打ち切る
+ *
+ * solve と hint は別スレッドで実行するので、応答は要求の順に並ぶとは限らない。
+ * 打ち切られた solve の termination は "cancelled" で、それまでに見つけた解を返す。
+ * 入力が終わったら実行中の solve と hint が終わるのを待って終了する。
+ */
+
+use std::collections::{ HashMap };
+use std::io::{ self, prelude::* };
+use std::panic;
+use std::str::{ FromStr };
+use std::sync::{ Arc, Mutex };
+use std::thread;
+use std::time;
+
+use failure;
+
+use ::{ Cancel, SolverErrorKind };
+use algo;
+use board::{ Board };
+use cli::{ Args };
+use json::{ Value };
+use report;
+use util;
+
+type Running = Arc<Mutex<HashMap<String, Cancel>>>;
+
+/// 局面の状態
+pub fn state_str(board: &Board) -> &'static str {
+    if board.is_solved() {
+        "solved"
+    }
+    else if board.is_stuck() || board.moves().is_empty() {
+        "stuck"
+    }
+    else {
+        "playing"
+    }
+}
+
+fn parse_board(value: Option<&Value>) -> Result<Board, failure::Error> {
+    let value = value.ok_or_else(|| format_err!("board missing"))?;
+    if let Some(s) = value.as_str() {
+        return Board::from_str(s);
+    }
+
+    let pos = match value.get("pos") {
+        Some(pos) => parse_move(pos)?,
+        None      => bail!("board: pos missing"),
+    };
+    let rows = value.get("rows").and_then(Value::as_array)
+        .ok_or_else(|| format_err!("board: rows missing"))?;
+    let mut s = {
+        let (x, y) = Board::idx2xy(pos);
+        format!("{} {}\n", x, y)
+    };
+    for row in rows {
+        if let Some(row) = row.as_str() {
+            s.push_str(row);
+        }
+        else if let Some(cells) = row.as_array() {
+            for cell in cells {
+                s.push_str(cell.as_str().ok_or_else(|| format_err!("board: a cell must be a string"))?);
+            }
+        }
+        else {
+            bail!("board: a row must be a string or an array");
+        }
+        s.push('\n');
+    }
+    Board::from_str(&s)
+}
+
+fn parse_move(value: &Value) -> Result<u8, failure::Error> {
+    let idx = match (value.as_i64(), value.as_array()) {
+        (Some(idx), _) => idx,
+        (_, Some([x, y])) => match (x.as_i64(), y.as_i64()) {
+            (Some(x), Some(y)) if (0..8).contains(&x) && (0..8).contains(&y) => 8*y + x,
+            _ => bail!("invalid move: {}", value),
+        },
+        _ => bail!("invalid move: {}", value),
+    };
+    if !(0..64).contains(&idx) { bail!("invalid move: {}", value); }
+    Ok(idx as u8)
+}
+
+fn parse_moves(value: Option<&Value>) -> Result<Vec<u8>, failure::Error> {
+    value.and_then(Value::as_array)
+        .ok_or_else(|| format_err!("moves missing"))?
+        .iter()
+        .map(parse_move)
+        .collect()
+}
+
+// options を "egypt solve" のコマンドライン引数と同じように解釈する
+fn parse_options(value: Option<&Value>) -> Result<Args, failure::Error> {
+    let mut argv = vec![];
+    match value {
+        None | Some(Value::Null) => {},
+        Some(Value::Object(entries)) => {
+            for (name, v) in entries {
+                if name == "checkpoint" || name == "resume" {
+                    bail!("option {} cannot be used with server", name);
+                }
+                match v {
+                    Value::Bool(true)    => argv.push(format!("--{}", name)),
+                    Value::Bool(false)   => {},
+                    Value::String(ref s) => argv.push(format!("--{}={}", name, s)),
+                    _                    => argv.push(format!("--{}={}", name, v)),
+                }
+            }
+        },
+        Some(_) => bail!("options must be an object"),
+    }
+    let args = Args::parse(&argv, algo::SOLVE_OPTIONS)?;
+    algo::algo_name(&args)?;
+    Ok(args)
+}
+
+// least_to_solve は解けておらず手詰まりでもない局面でしか求まらないので、それ以外は null
+fn moves_json(board: &Board) -> Value {
+    let state = state_str(board);
+    let least = if state == "playing" { Some(board.least_to_solve()) } else { None };
+    Value::object()
+        .with("moves",          report::moves_json(&board.moves()))
+        .with("state",          state)
+        .with("least_to_solve", least)
+}
+
+fn apply_json(board: &Board, moves: &[u8]) -> Result<Value, failure::Error> {
+    let mut board = board.clone();
+    let mut step = 0;
+    for &to in moves {
+        if !board.moves().contains(&to) {
+            let (x, y) = Board::idx2xy(to);
+            bail!("illegal move: [{},{}]", x, y);
+        }
+        step += board.calc_step(board.pos, to).unwrap();
+        board.move_(to);
+    }
+    Ok(Value::object()
+        .with("board",  report::board_json(&board))
+        .with("state",  state_str(&board))
+        .with("rotate", moves.len())
+        .with("step",   step))
+}
+
+fn verify_json(board: &Board, moves: &[u8]) -> Value {
+    let valid = util::verify_solution(board, moves);
+    let step = if valid { Some(util::step_of_solution(board, moves)) } else { None };
+    Value::object()
+        .with("valid",  valid)
+        .with("rotate", moves.len())
+        .with("step",   step)
+}
+
+// solve と hint の本体
+fn solve(method: &str, board: &Board, args: &Args, cancel: &Cancel) -> Result<Value, failure::Error> {
+    let mut solver = algo::build_solver_with_cancel(args, cancel)?;
+    let t = time::Instant::now();
+    let r = solver.solve(board);
+    let time = util::duration_float(&t.elapsed());
+    let (sols, termination) = match r {
+        Ok(sols)                        => {
+            let termination = if sols.is_empty() { "unsolved" } else { "solved" };
+            (sols, termination)
+        },
+        Err(e) => match *e.kind() {
+            SolverErrorKind::Cancelled => (e.solutions().to_vec(), "cancelled"),
+            SolverErrorKind::Limit     => (e.solutions().to_vec(), "limit"),
+            SolverErrorKind::Io(_)     => return Err(e.into()),
+        },
+    };
+
+    if method == "solve" {
+        return Ok(report::solve_json(algo::algo_name(args)?, board, termination, &sols, &*solver, time));
+    }
+    let best = util::solutions_with_step(board, &sols).into_iter().next();
+    Ok(Value::object()
+        .with("termination", termination)
+        .with("move",        best.as_ref().and_then(|(sol, _)| sol.first()).map(|&to| report::move_json(to)))
+        .with("rotate",      best.as_ref().map(|(sol, _)| sol.len()))
+        .with("step",        best.as_ref().map(|&(_, step)| step)))
+}
+
+/// 要求 1 つに対する応答の中身。solve と hint 以外はすぐに処理できる
+pub fn handle(request: &Value) -> Result<Value, failure::Error> {
+    let method = request.get("method").and_then(Value::as_str)
+        .ok_or_else(|| format_err!("method missing"))?;
+    let board = || parse_board(request.get("board"));
+    match method {
+        "moves"  => Ok(moves_json(&board()?)),
+        "apply"  => apply_json(&board()?, &parse_moves(request.get("moves"))?),
+        "verify" => Ok(verify_json(&board()?, &parse_moves(request.get("moves"))?)),
+        "solve" | "hint" => solve(method, &board()?, &parse_options(request.get("options"))?, &Cancel::new()),
+        _        => bail!("unknown method: {}", method),
+    }
+}
+
+// f を実行し、panic したらエラーにする(1 つの要求のためにサーバーを落とさない)
+fn catch_panic<F>(f: F) -> Result<Value, failure::Error>
+    where F: FnOnce() -> Result<Value, failure::Error>
+{
+    panic::catch_unwind(panic::AssertUnwindSafe(f))
+        .unwrap_or_else(|payload| Err(format_err!("internal error: {}", util::panic_message(&*payload))))
+}
+
+fn respond<W: Write>(w: &Mutex<W>, id: &Value, r: Result<Value, failure::Error>) {
+    let response = match r {
+        Ok(result) => Value::object().with("id", id.clone()).with("result", result),
+        Err(e)     => Value::object().with("id", id.clone()).with("error", e.to_string()),
+    };
+    let mut w = w.lock().unwrap();
+    // 書けなければ相手がいなくなったので、何もしない
+    let _ = writeln!(w, "{}", response).and_then(|_| w.flush());
+}
+
+/// r から要求を読み、応答を w に書く。r が終わったら実行中の solve, hint を待って返る
+pub fn serve<R, W>(r: R, w: W) -> io::Result<()>
+    where R: BufRead, W: Write + Send + 'static
+{
+    let w = Arc::new(Mutex::new(w));
+    let running: Running = Arc::new(Mutex::new(HashMap::new()));
+    let mut handles = vec![];
+
+    for line in r.lines() {
+        let line = line?;
+        if line.trim().is_empty() { continue; }
+        let request = match Value::from_str(&line) {
+            Ok(request) => request,
+            Err(e)      => { respond(&w, &Value::Null, Err(e)); continue; },
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = request.get("method").and_then(Value::as_str).unwrap_or("");
+
+        match method {
+            "cancel" => {
+                let target = request.get("target").map(|v| v.to_string());
+                let r = match target.and_then(|target| running.lock().unwrap().get(&target).cloned()) {
+                    Some(cancel) => { cancel.cancel(); Ok(true) },
+                    None         => Ok(false),
+                };
+                respond(&w, &id, r.map(|found| Value::object().with("cancelled", found)));
+            },
+            "solve" | "hint" => {
+                let parsed = parse_board(request.get("board"))
+                    .and_then(|board| Ok((board, parse_options(request.get("options"))?)));
+                let (board, args) = match parsed {
+                    Ok(parsed) => parsed,
+                    Err(e)     => { respond(&w, &id, Err(e)); continue; },
+                };
+                let key = id.to_string();
+                let cancel = Cancel::new();
+                {
+                    let mut running = running.lock().unwrap();
+                    if running.contains_key(&key) {
+                        respond(&w, &id, Err(format_err!("id {} is already running", key)));
+                        continue;
+                    }
+                    running.insert(key.clone(), cancel.clone());
+                }
+
+                let method = method.to_owned();
+                let w = Arc::clone(&w);
+                let running = Arc::clone(&running);
+                handles.push(thread::spawn(move || {
+                    let r = catch_panic(|| solve(&method, &board, &args, &cancel));
+                    running.lock().unwrap().remove(&key);
+                    respond(&w, &id, r);
+                }));
+            },
+            _ => respond(&w, &id, catch_panic(|| handle(&request))),
+        }
+        handles.retain(|h: &thread::JoinHandle<()>| !h.is_finished());
+    }
+
+    // solve と hint の panic は catch_panic() で捕まえている
+    for h in handles {
+        let _ = h.join();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_server() {
+    let board = "\
+1 1
+#######4
+#010...#
+#.<1.^2#
+#.2.1..#
+3......#
+#.v..>.3
+#......v
+#######4
+";
+    let request = |s: &str| -> Value {
+        let mut request = Value::from_str(s).unwrap();
+        if let Value::Object(ref mut entries) = request {
+            entries.push(("board".to_owned(), Value::from(board)));
+        }
+        request
+    };
+
+    let res = handle(&request(r#"{"method":"moves"}"#)).unwrap();
+    assert_eq!(r#"{"moves":[[2,2],[5,2],[2,5],[5,5],[7,6]],"state":"playing","least_to_solve":4}"#, res.to_string());
+
+    let res = handle(&request(r#"{"method":"apply","moves":[[5,2]]}"#)).unwrap();
+    assert_eq!(Some("playing"), res.get("state").and_then(Value::as_str));
+    assert_eq!(Some(1), res.get("rotate").and_then(Value::as_i64));
+    assert_eq!(res, handle(&request(r#"{"method":"apply","moves":[21]}"#)).unwrap());
+    assert!(handle(&request(r#"{"method":"apply","moves":[[0,0]]}"#)).is_err());
+    assert!(handle(&request(r#"{"method":"apply","moves":[[8,0]]}"#)).is_err());
+
+    let res = handle(&request(r#"{"method":"verify","moves":[[2,2]]}"#)).unwrap();
+    assert_eq!(r#"{"valid":false,"rotate":1,"step":null}"#, res.to_string());
+
+    // 局面はセルの配列でもよい
+    let rows: Vec<Value> = board.lines().skip(1)
+        .map(|row| Value::from(row.chars().map(|c| c.to_string()).collect::<Vec<_>>()))
+        .collect();
+    let cells = Value::object()
+        .with("method", "moves")
+        .with("board",  Value::object().with("pos", vec![1u8, 1]).with("rows", rows));
+    assert_eq!(handle(&request(r#"{"method":"moves"}"#)).unwrap(), handle(&cells).unwrap());
+
+    assert!(handle(&Value::from_str(r#"{"method":"moves","board":"1 1\n###"}"#).unwrap()).is_err());
+    // 解けた局面や手詰まりの局面では least_to_solve は求まらない
+    let stuck = Value::object().with("method", "moves").with("board", board.replace("#010", "#.10"));
+    let res = handle(&stuck).unwrap();
+    assert_eq!(Some("stuck"), res.get("state").and_then(Value::as_str));
+    assert_eq!(Some(&Value::Null), res.get("least_to_solve"));
+    assert!(handle(&request(r#"{"method":"frobnicate"}"#)).is_err());
+    assert!(handle(&request(r#"{"method":"solve","options":{"algo":"x"}}"#)).is_err());
+}
+
+#[test]
+fn test_catch_panic() {
+    let e = catch_panic(|| panic!("boom")).unwrap_err();
+    assert_eq!("internal error: boom", e.to_string());
+    assert!(catch_panic(|| Ok(Value::Null)).is_ok());
+}
@@ -1,6 +1,10 @@
+use std::any::{ Any };
 use std::collections::{ VecDeque };
 use std::time;
 
+use failure;
+use rand::{ SeedableRng, prng::{ XorShiftRng } };
+
 use board::{ Board };
 
 #[derive(Debug)]
@@ -38,10 +42,41 @@ impl<T> Queue<T> {
     }
 }
 
+impl<T> Default for Queue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// catch_unwind() で捕まえた panic の内容を文字列にする
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_owned())
+}
+
 pub fn duration_float(dur: &time::Duration) -> f64 {
     dur.as_secs() as f64 + 1e-9 * f64::from(dur.subsec_nanos())
 }
 
+// シードから乱数生成器を作る(同じシードなら同じ乱数列になる)
+// u64 を splitmix64 で XorShiftRng のシード長まで引き伸ばす
+// StdRng などブロック単位で生成するもの(rand_core 0.2 の BlockRng)は unsafe な読み出しが
+// debug ビルドの検査に引っかかって panic するので使わない
+pub fn seeded_rng(seed: u64) -> XorShiftRng {
+    let mut x = seed;
+    let mut buf = <XorShiftRng as SeedableRng>::Seed::default();
+    for chunk in buf.chunks_mut(8) {
+        x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = x;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        chunk.copy_from_slice(&z.to_le_bytes()[..chunk.len()]);
+    }
+    XorShiftRng::from_seed(buf)
+}
+
 // sol の無駄手を省いた解のリストを返す
 pub fn optimize_solution(board: &Board, sol: &[u8]) -> Vec<Vec<u8>> {
     let mut cur = vec![sol.to_vec()];
@@ -67,6 +102,24 @@ pub fn optimize_solution(board: &Board, sol: &[u8]) -> Vec<Vec<u8>> {
     cur
 }
 
+// sol から解のままで取り除ける手を先頭から順に取り除く
+// optimize_solution() と違い 1 通りしか調べないが、手数によらず速い
+pub fn remove_redundant(board: &Board, sol: &[u8]) -> Vec<u8> {
+    let mut sol = sol.to_vec();
+    let mut i = 0;
+    while i < sol.len() {
+        let mut sol2 = sol.clone();
+        sol2.remove(i);
+        if verify_solution(board, &sol2) {
+            sol = sol2;
+        }
+        else {
+            i += 1;
+        }
+    }
+    sol
+}
+
 // sol が board の解になっていれば true を返す
 // sol の途中で解けた場合も true を返すことに注意
 // (false を返すべきかもしれないが、解を見落とすよりはマシだろう)
@@ -108,7 +161,7 @@ pub fn solutions_with_step(board: &Board, sols: &[Vec<u8>]) -> Vec<(Vec<u8>,u32)
 }
 
 // '#' 以下は読み飛ばす
-pub fn parse_solution(line: &str) -> Vec<u8> {
+pub fn parse_solution(line: &str) -> Result<Vec<u8>, failure::Error> {
     let line = if let Some(i) = line.find('#') {
         &line[..i]
     }
@@ -116,15 +169,30 @@ pub fn parse_solution(line: &str) -> Vec<u8> {
         line
     };
 
-    let res = line.split_whitespace()
-        .map(|w| w.parse().unwrap())
-        .collect();
+    line.split_whitespace()
+        .map(|w| w.parse().map_err(|e| format_err!("invalid move: {}: {}", w, e)))
+        .collect()
+}
 
-    res
+/// 1 行 1 解のテキストを解のリストにする(空行と '#' で始まる行は無視)
+pub fn parse_solutions(s: &str) -> Result<Vec<Vec<u8>>, failure::Error> {
+    s.lines()
+        .filter(|line| {
+            let line = line.trim();
+            if line.is_empty() { return false; }
+            if line.starts_with('#') { return false; }
+            true
+        })
+        .map(parse_solution)
+        .collect()
 }
 
 #[test]
 fn test_parse_solution() {
-    assert_eq!(vec![0,1,2,3], parse_solution("0 1 2 3"));
-    assert_eq!(vec![3,5,2,7], parse_solution("  3 5  2  7  # foobar  "));
+    assert_eq!(vec![0,1,2,3], parse_solution("0 1 2 3").unwrap());
+    assert_eq!(vec![3,5,2,7], parse_solution("  3 5  2  7  # foobar  ").unwrap());
+    assert_eq!(vec![vec![0,1], vec![2]], parse_solutions("# comment\n0 1 # rotate=2\n\n2\n").unwrap());
+    assert!(parse_solution("1 x 3").is_err());
+    assert!(parse_solution("1 300").is_err());
+    assert!(parse_solutions("0 1\n2 -1\n").is_err());
 }
@@ -0,0 +1,48 @@
+extern crate egypt;
+
+use std::fs;
+use std::str::{ FromStr };
+use std::time;
+
+use egypt::board::{ Board };
+use egypt::util;
+
+use egypt::{ Solver };
+use egypt::anneal::{ AnnealSolver, Schedule };
+
+#[test]
+fn test_anneal() {
+    let s = fs::read_to_string("problem/10-5.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+
+    for &schedule in &[
+        Schedule::Geometric { start: 2000.0, end: 10.0 },
+        Schedule::Linear { start: 1000.0, end: 1.0 },
+    ] {
+        let sols = AnnealSolver::new(30, time::Duration::from_millis(2000))
+            .schedule(schedule)
+            .seed(2)
+            .solve(&board)
+            .unwrap();
+        assert!(!sols.is_empty());
+        for sol in &sols {
+            assert!(util::verify_solution(&board, sol));
+            assert_eq!(sols[0].len(), sol.len());
+        }
+    }
+}
+
+#[test]
+fn test_anneal_trivial() {
+    // 解けている局面は空の解、打てる手が無い局面は解無し
+    let solved = Board::from_str(&format!("0 0{}", "\n........".repeat(8))).unwrap();
+    let no_move = Board::from_str(&format!("0 0\n.1....1.{}", "\n........".repeat(7))).unwrap();
+    let cases: Vec<(Board, Vec<Vec<u8>>)> = vec![(solved, vec![vec![]]), (no_move, vec![])];
+    for (board, expected) in cases {
+        let sols = AnnealSolver::new(5, time::Duration::from_millis(100))
+            .seed(1)
+            .solve(&board)
+            .unwrap();
+        assert_eq!(expected, sols);
+    }
+}
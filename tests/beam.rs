@@ -0,0 +1,61 @@
+extern crate egypt;
+
+use std::fs;
+use std::path;
+use std::str::{ FromStr };
+
+use egypt::board::{ Board };
+use egypt::util;
+
+use egypt::{ Solver, SolverErrorKind };
+use egypt::beam::{ BeamSolver };
+use egypt::bfs::{ BfsSolver };
+
+#[test]
+fn test_beam() {
+    const PROBLEMS: &[&str] = &[
+        "00-0.in",
+        "01-2.in",
+        "02-4.in",
+        "05-4.in",
+        "10-5.in",
+        "17-5.in",
+    ];
+
+    let mut solver_bfs  = BfsSolver::new(10_000_000);
+    let mut solver_beam = BeamSolver::new(1000, 100);
+
+    for filename in PROBLEMS {
+        let path = path::Path::new("problem/").join(filename);
+        let s = fs::read_to_string(path).unwrap();
+        let board = Board::from_str(&s).unwrap();
+
+        let len_opt = solver_bfs.solve(&board).unwrap()[0].len();
+        let sols = solver_beam.solve(&board).unwrap();
+        assert!(!sols.is_empty());
+        for sol in &sols {
+            assert!(util::verify_solution(&board, sol));
+            assert!(sol.len() >= len_opt);
+        }
+
+        // 決定的
+        assert_eq!(sols, solver_beam.solve(&board).unwrap());
+    }
+}
+
+#[test]
+fn test_beam_limit() {
+    let s = fs::read_to_string("problem/17-5.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+
+    // 幅や深さで打ち切って解が無ければ、解なしではなく上限に達したものとする
+    for &(width, max_depth) in &[(1000, 1), (1, 100)] {
+        let e = BeamSolver::new(width, max_depth).solve(&board).unwrap_err();
+        assert_eq!(&SolverErrorKind::Limit, e.kind());
+        assert!(e.solutions().is_empty());
+    }
+
+    // 打てる手が無い局面は解が無い
+    let board = Board::from_str(&format!("0 0\n.1....1.{}", "\n........".repeat(7))).unwrap();
+    assert_eq!(Vec::<Vec<u8>>::new(), BeamSolver::new(1000, 100).solve(&board).unwrap());
+}
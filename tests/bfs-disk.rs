@@ -0,0 +1,50 @@
+extern crate egypt;
+
+mod common;
+
+use std::fs;
+use std::path;
+use std::str::{ FromStr };
+
+use egypt::board::{ Board };
+
+use egypt::{ Solver, SolverErrorKind };
+use egypt::bfs::{ BfsSolver };
+use egypt::bfs_disk::{ DiskBfsSolver };
+
+#[test]
+fn test_bfs_disk() {
+    const MAX_NODE_COUNT_BFS: u64 = 10_000_000;
+
+    let mut solver_bfs      = BfsSolver::new(MAX_NODE_COUNT_BFS);
+    let mut solver_bfs_disk = DiskBfsSolver::with_temp_dir(MAX_NODE_COUNT_BFS).memory(10_000);
+
+    for room in common::ROOMS_EASY {
+        let path = path::Path::new("problem/").join(format!("{}.in", room));
+        eprintln!("{}", path.to_str().unwrap());
+        let s = fs::read_to_string(path).unwrap();
+        let board = Board::from_str(&s).unwrap();
+
+        let mut sols_bfs = solver_bfs.solve(&board).unwrap();
+        sols_bfs.sort();
+        let mut sols_bfs_disk = solver_bfs_disk.solve(&board).unwrap();
+        sols_bfs_disk.sort();
+        assert_eq!(sols_bfs, sols_bfs_disk);
+    }
+}
+
+#[test]
+fn test_bfs_disk_io_error() {
+    let s = fs::read_to_string("problem/00-2.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+
+    // 作業用ディレクトリを作れなければ panic せずにエラーを返す
+    let mut solver = DiskBfsSolver::new("/dev/null/egypt", 1000);
+    match solver.solve(&board) {
+        Err(e) => match *e.kind() {
+            SolverErrorKind::Io(_) => {},
+            ref kind               => panic!("unexpected error: {:?}", kind),
+        },
+        Ok(sols) => panic!("unexpected solutions: {:?}", sols),
+    }
+}
@@ -0,0 +1,58 @@
+extern crate egypt;
+
+mod common;
+
+use std::fs;
+use std::path;
+use std::str::{ FromStr };
+
+use egypt::board::{ Board };
+
+use egypt::{ Solver };
+use egypt::bfs::{ BfsSolver };
+
+fn read_board(room: &str) -> Board {
+    let path = path::Path::new("problem/").join(format!("{}.in", room));
+    eprintln!("{}", path.to_str().unwrap());
+    let s = fs::read_to_string(path).unwrap();
+    Board::from_str(&s).unwrap()
+}
+
+#[test]
+fn test_bfs_par() {
+    const MAX_NODE_COUNT_BFS: u64 = 10_000_000;
+
+    let mut solver_bfs     = BfsSolver::new(MAX_NODE_COUNT_BFS);
+    let mut solver_bfs_par = BfsSolver::new(MAX_NODE_COUNT_BFS).threads(4);
+
+    for room in common::ROOMS_EASY {
+        let board = read_board(room);
+
+        let mut sols_bfs = solver_bfs.solve(&board).unwrap();
+        sols_bfs.sort();
+        let mut sols_bfs_par = solver_bfs_par.solve(&board).unwrap();
+        sols_bfs_par.sort();
+        assert_eq!(sols_bfs, sols_bfs_par);
+    }
+}
+
+#[test]
+fn test_bfs_par_limit() {
+    // 上限はスレッド全体の子の数に対するもので、直列版と同じところで打ち切る
+    for &max_node_count in &[100, 1_000, 10_000, 100_000] {
+        for room in &["02-0", "05-0", "13-0", "17-5"] {
+            let board = read_board(room);
+            let r     = BfsSolver::new(max_node_count).solve(&board);
+            let r_par = BfsSolver::new(max_node_count).threads(4).solve(&board);
+            match (r, r_par) {
+                (Ok(mut sols), Ok(mut sols_par)) => {
+                    sols.sort();
+                    sols_par.sort();
+                    assert_eq!(sols, sols_par);
+                },
+                (Err(e), Err(e_par)) => assert_eq!(e.kind(), e_par.kind()),
+                (r, r_par)           => panic!("{}: {:?} != {:?}", room, r, r_par),
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+extern crate egypt;
+
+use std::fs;
+use std::path;
+use std::str::{ FromStr };
+
+use egypt::board::{ Board };
+
+use egypt::{ Solver };
+use egypt::beam::{ BeamSolver };
+use egypt::bnb::{ BnbSolver };
+use egypt::ida::{ IdaSolver };
+
+#[test]
+fn test_bnb() {
+    const PROBLEMS: &[&str] = &[
+        "00-0.in",
+        "02-4.in",
+        "05-4.in",
+        "10-5.in",
+        "17-5.in",
+    ];
+
+    for filename in PROBLEMS {
+        let path = path::Path::new("problem/").join(filename);
+        let s = fs::read_to_string(path).unwrap();
+        let board = Board::from_str(&s).unwrap();
+
+        let mut sols_ida = IdaSolver::new(0, 1_000_000_000).solve(&board).unwrap();
+        sols_ida.sort();
+
+        // 上界が悪くても(得られなくても)最適解を全て求める
+        for &(width, max_depth, n_thread) in &[(1, 100, 1), (1000, 100, 1), (1000, 100, 4), (1000, 1, 1)] {
+            let mut solver = BnbSolver::new(1_000_000_000)
+                .upper_bound_solver(BeamSolver::new(width, max_depth))
+                .threads(n_thread);
+            let mut sols = solver.solve(&board).unwrap();
+            sols.sort();
+            assert_eq!(sols_ida, sols);
+            let len = sols[0].len() as u32;
+            assert_eq!((len, Some(len)), solver.bounds());
+        }
+    }
+}
+
+#[test]
+fn test_bnb_limit() {
+    let s = fs::read_to_string("problem/17-5.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+
+    // 上限に達したら上界の解を返す
+    let mut solver = BnbSolver::new(10);
+    let sols = solver.solve(&board).unwrap_err().solutions().to_vec();
+    assert!(!sols.is_empty());
+    let (lower, upper) = solver.bounds();
+    assert_eq!(Some(sols[0].len() as u32), upper);
+    assert!(lower <= upper.unwrap());
+}
+
+#[test]
+fn test_bnb_unsolvable() {
+    // 打てる手が無い(手詰まりではない)局面は解が無い
+    let board: Board = "\
+0 0
+........
+.1......
+........
+........
+........
+........
+......1.
+........
+".parse().unwrap();
+    let mut solver = BnbSolver::new(1_000_000);
+    assert_eq!(Vec::<Vec<u8>>::new(), solver.solve(&board).unwrap());
+    assert_eq!((0, None), solver.bounds());
+
+    // 同じ矢印を踏み続けるしかない局面は、ノード数の上限で打ち切る
+    let board: Board = "\
+0 0
+.>......
+........
+........
+####....
+.1.#....
+####....
+......1.
+........
+".parse().unwrap();
+    let mut solver = BnbSolver::new(10_000);
+    let e = solver.solve(&board).unwrap_err();
+    assert!(e.solutions().is_empty());
+    assert!(solver.node_count().unwrap() <= 10_000 + 1024);
+    assert_eq!(None, solver.bounds().1);
+}
@@ -0,0 +1,100 @@
+/*
+ * C API のテスト
+ *
+ * cc -Iinclude tests/c/test_ffi.c -Ltarget/release -legypt -o test_ffi
+ * LD_LIBRARY_PATH=target/release ./test_ffi problem/02-0.in
+ *
+ * 問題ファイルを読み、打てる手を調べ、IDA* で解いた解を打って解けることを確かめる。
+ */
+
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "egypt.h"
+
+#define CHECK(cond) do { \
+    if (!(cond)) { \
+        fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+        exit(1); \
+    } \
+} while (0)
+
+static char *read_file(const char *path) {
+    FILE *fp = fopen(path, "rb");
+    CHECK(fp != NULL);
+    static char buf[4096];
+    size_t n = fread(buf, 1, sizeof(buf)-1, fp);
+    buf[n] = '\0';
+    fclose(fp);
+    return buf;
+}
+
+int main(int argc, char **argv) {
+    CHECK(argc == 2);
+
+    /* 壊れた局面はエラー */
+    CHECK(egypt_board_parse("1 1\n###") == NULL);
+    CHECK(egypt_last_error() != NULL);
+
+    EgyptBoard *board = egypt_board_parse(read_file(argv[1]));
+    CHECK(board != NULL);
+    CHECK(!egypt_board_is_solved(board));
+    CHECK(!egypt_board_is_stuck(board));
+
+    uint8_t moves[64];
+    size_t n_move = egypt_board_moves(board, moves, 64);
+    CHECK(n_move > 0);
+    CHECK(egypt_board_moves(board, NULL, 0) == n_move);
+    printf("pos: %u\nmoves:", egypt_board_pos(board));
+    for (size_t i = 0; i < n_move; ++i) printf(" %u", moves[i]);
+    printf("\nleast_to_solve: %d\n", egypt_board_least_to_solve(board));
+
+    char *s = egypt_board_to_string(board);
+    CHECK(s != NULL);
+    egypt_string_free(s);
+
+    CHECK(egypt_solve(board, "--algo nothing") == NULL);
+    printf("error: %s\n", egypt_last_error());
+
+    EgyptResult *result = egypt_solve(board, "--algo ida");
+    CHECK(result != NULL);
+    CHECK(egypt_result_status(result) == EGYPT_SOLVED);
+    size_t n_sol = egypt_result_count(result);
+    CHECK(n_sol > 0);
+    CHECK(egypt_result_nodes(result) > 0);
+
+    for (size_t i = 0; i < n_sol; ++i) {
+        uint8_t sol[256];
+        size_t len = egypt_result_solution(result, i, sol, 256);
+        CHECK(len <= 256);
+        CHECK(egypt_board_least_to_solve(board) > 0);
+        CHECK(len >= (size_t)egypt_board_least_to_solve(board));
+
+        /* 解を打つと解け、歩数も一致する */
+        EgyptBoard *b = egypt_board_clone(board);
+        uint32_t step = 0;
+        for (size_t k = 0; k < len; ++k) {
+            int r = egypt_board_apply(b, sol[k]);
+            CHECK(r >= 0);
+            step += (uint32_t)r;
+        }
+        CHECK(egypt_board_is_solved(b));
+        CHECK(!egypt_board_is_stuck(b));
+        CHECK(step == egypt_result_step(result, i));
+        egypt_board_free(b);
+
+        printf("solution:");
+        for (size_t k = 0; k < len; ++k) printf(" %u", sol[k]);
+        printf(" # rotate=%zu step=%u\n", len, step);
+    }
+    CHECK(egypt_result_solution(result, n_sol, NULL, 0) == 0);
+    egypt_result_free(result);
+
+    /* 打てない手は局面を変えない */
+    CHECK(egypt_board_apply(board, 0) == -1);
+    CHECK(egypt_board_moves(board, NULL, 0) == n_move);
+
+    egypt_board_free(board);
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,123 @@
+extern crate egypt;
+
+use std::env;
+use std::fs;
+use std::process;
+use std::str::{ FromStr };
+use std::time;
+
+use egypt::board::{ Board };
+
+use egypt::{ Solver, StreamSolver };
+use egypt::checkpoint::{ Checkpoint };
+use egypt::ida::{ IdaSolver };
+use egypt::iddfs::{ IddfsSolver };
+
+// 解を子の番号の列に直す
+fn to_path(board: &Board, sol: &[u8]) -> Vec<u32> {
+    let mut board = board.clone();
+    sol.iter()
+        .map(|&to| {
+            let i = board.moves().iter().position(|&e| e == to).unwrap();
+            board.move_(to);
+            i as u32
+        })
+        .collect()
+}
+
+#[test]
+fn test_resume() {
+    let s = fs::read_to_string("problem/02-0.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+
+    let mut sols = IdaSolver::new(0, 1_000_000_000).solve(&board).unwrap();
+    sols.sort();
+    let max_depth = sols[0].len() as u32;
+
+    for progress in &[vec![], vec![1], vec![1, 2], vec![2, 0, 3]] {
+        // progress より前の部分木は探索済みで、そこで見つかった解を持っているとする
+        let ckpt = Checkpoint {
+            board:      board.clone(),
+            max_depth,
+            node_count: 0,
+            progress:   progress.clone(),
+            solutions:  sols.iter()
+                .filter(|sol| to_path(&board, sol) < *progress)
+                .cloned()
+                .collect(),
+        };
+
+        let mut sols_ida = IdaSolver::new(0, 1_000_000_000).resume(ckpt.clone()).solve(&board).unwrap();
+        sols_ida.sort();
+        assert_eq!(sols, sols_ida);
+
+        let mut sols_ida_par = IdaSolver::new(0, 1_000_000_000).threads(3).resume(ckpt.clone()).solve(&board).unwrap();
+        sols_ida_par.sort();
+        assert_eq!(sols, sols_ida_par);
+
+        let mut sols_iddfs = IddfsSolver::new(0, 1_000_000_000).resume(ckpt).solve(&board).unwrap();
+        sols_iddfs.sort();
+        assert_eq!(sols, sols_iddfs);
+    }
+}
+
+#[test]
+fn test_checkpoint_abort_resume() {
+    let s = fs::read_to_string("problem/02-0.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+
+    let mut sols = IdaSolver::new(0, 1_000_000_000).solve(&board).unwrap();
+    sols.sort();
+
+    for &n_thread in &[1, 3] {
+        let path = env::temp_dir().join(format!("egypt-test-resume-{}-{}", process::id(), n_thread));
+
+        // 途中で探索を打ち切る
+        let mut n = 0;
+        IdaSolver::new(0, 1_000_000_000)
+            .threads(n_thread)
+            .checkpoint(path.clone(), time::Duration::from_secs(0))
+            .solve_stream(&board, &mut |_, _| {
+                n += 1;
+                n < sols.len() / 2
+            })
+            .unwrap();
+
+        let ckpt = Checkpoint::load(&path).unwrap();
+        assert!(!ckpt.progress.is_empty());
+        let mut sols_resumed = IdaSolver::new(0, 1_000_000_000)
+            .threads(n_thread)
+            .checkpoint(path.clone(), time::Duration::from_secs(0))
+            .resume(ckpt)
+            .solve(&board)
+            .unwrap();
+        sols_resumed.sort();
+        assert_eq!(sols, sols_resumed);
+        assert!(!path.exists());
+    }
+}
+
+#[test]
+fn test_checkpoint_corrupt() {
+    let s = fs::read_to_string("problem/02-0.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+    let path = env::temp_dir().join(format!("egypt-test-corrupt-{}", process::id()));
+
+    let ckpt = Checkpoint {
+        board,
+        max_depth:  8,
+        node_count: 100,
+        progress:   vec![1, 2],
+        solutions:  vec![vec![28, 29]],
+    };
+    ckpt.save(&path).unwrap();
+    let text = fs::read_to_string(&path).unwrap();
+    assert!(text.contains("solution 28 29\n"));
+
+    // 壊れた記録は panic せずエラーにする
+    for (from, to) in &[("solution 28 29\n", "solution 28 x\n"), ("progress 1 2\n", "progress 1 -2\n"), ("board\n", "")] {
+        fs::write(&path, text.replacen(from, to, 1)).unwrap();
+        assert!(Checkpoint::load(&path).is_err(), "{} -> {}", from, to);
+    }
+    fs::remove_file(&path).unwrap();
+}
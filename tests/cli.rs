@@ -0,0 +1,215 @@
+use std::env;
+use std::fs;
+use std::io::{ prelude::* };
+use std::process::{ self, Command, Output, Stdio };
+
+// キャッシュはテストごとのディレクトリに置く
+// --cache-dir を付けない既定のキャッシュも、利用者のものではなくそこを使わせる
+fn cache_dir(name: &str) -> String {
+    env::temp_dir().join(format!("egypt-test-cli-{}-{}", name, process::id())).to_str().unwrap().to_owned()
+}
+
+fn egypt_in(cache: &str, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_egypt"))
+        .args(args)
+        .env("EGYPT_CACHE_DIR", cache)
+        .output()
+        .unwrap()
+}
+
+fn egypt(args: &[&str]) -> Output {
+    egypt_in(&cache_dir("cache"), args)
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn test_cli() {
+    let out = egypt(&["solve", "--algo", "ida", "problem/02-0.in"]);
+    assert!(out.status.success());
+    let sols = stdout(&out);
+    assert!(sols.lines().count() > 0);
+    assert!(sols.lines().all(|line| line.contains("# rotate=8 ")));
+
+    let path = env::temp_dir().join(format!("egypt-test-cli-{}", process::id()));
+    fs::write(&path, format!("{}1 2 3\n", sols)).unwrap();
+    let out = egypt(&["verify", path.to_str().unwrap(), "problem/02-0.in"]);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(Some(1), out.status.code());
+    let lines: Vec<_> = stdout(&out).lines().map(String::from).collect();
+    assert!(lines[..lines.len()-1].iter().all(|line| line.starts_with("ok ")));
+    assert_eq!("NG 1 2 3", lines[lines.len()-1]);
+
+    // 手が数でない解はエラーとして報告する(panic しない)
+    fs::write(&path, "1 x 3\n").unwrap();
+    let out = egypt(&["verify", path.to_str().unwrap(), "problem/02-0.in"]);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(Some(1), out.status.code());
+    assert!(stderr(&out).starts_with("egypt: invalid move: x"), "{}", stderr(&out));
+    let out = egypt(&["show", "--moves", "28 y", "problem/02-0.in"]);
+    assert_eq!(Some(1), out.status.code());
+    assert!(stderr(&out).starts_with("egypt: invalid move: y"), "{}", stderr(&out));
+
+    let out = egypt(&["solve", "--no-cache", "--format", "json", "problem/02-0.in"]);
+    assert!(out.status.success());
+    let json = stdout(&out);
+    assert_eq!(1, json.lines().count());
+    assert!(json.starts_with(r#"{"algo":"ida","board":{"pos":[3,3],"#));
+    assert!(json.contains(r#""termination":"solved","solutions":[{"moves":[["#));
+    assert!(json.contains(r#""rotate":8,"#));
+    assert!(json.contains(r#""depths":[{"depth":"#));
+    assert!(!egypt(&["solve", "--format", "csv", "problem/02-0.in"]).status.success());
+
+    let out = egypt(&["show", "--moves", "28", "problem/02-0.in"]);
+    assert!(out.status.success());
+    assert!(stdout(&out).contains("move 28\n"));
+
+    // 使わないオプションや不明なオプションはエラー
+    assert!(!egypt(&["solve", "--algo", "beam", "--reduce", "problem/02-0.in"]).status.success());
+    assert!(!egypt(&["solve", "--unknown", "problem/02-0.in"]).status.success());
+    assert!(!egypt(&["solve", "--algo", "genetic", "problem/02-0.in"]).status.success());
+    assert!(!egypt(&["frobnicate"]).status.success());
+
+    let out = egypt(&["batch", "--format", "csv", "--algo", "ida", "problem/0?-0.in", "--max-nodes", "100000"]);
+    assert_eq!(Some(1), out.status.code());  // 上限に達する部屋がある
+    let csv = stdout(&out);
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(11, lines.len());
+    assert_eq!("room,status,rotate,step,nodes,time,solutions", lines[0]);
+    assert!(lines[1].starts_with("00-0,solved,17,17,"));
+    assert!(lines.iter().any(|line| line.contains(",limit,")));
+    assert!(!egypt(&["batch", "--resume", "x", "problem/00-0.in"]).status.success());
+    let out = egypt(&["solve", "--resume", "x", "problem/00-0.in"]);
+    assert!(!out.status.success());
+    assert!(stderr(&out).contains("do not give a problem file"));
+
+    // 上限に達したら理由を表示する
+    let out = egypt(&["solve", "--no-cache", "--max-nodes", "100", "problem/17-5.in"]);
+    assert_eq!(Some(1), out.status.code());
+    assert!(stderr(&out).contains("too many nodes (solutions found so far: 0)"));
+    let out = egypt(&["solve", "--no-cache", "--max-nodes", "100", "--format", "json", "problem/17-5.in"]);
+    assert!(stdout(&out).contains(r#""termination":"limit""#));
+
+    // 作業用ファイルの失敗は上限と区別する
+    let out = egypt(&["solve", "--no-cache", "--algo", "bfs", "--disk", "/dev/null/egypt", "--format", "json", "problem/02-0.in"]);
+    assert_eq!(Some(1), out.status.code());
+    assert!(stdout(&out).contains(r#""termination":"error""#), "{}", stdout(&out));
+    assert!(stdout(&out).contains(r#""error":"I/O error: "#), "{}", stdout(&out));
+
+    let out = egypt(&["help", "solve"]);
+    assert!(out.status.success());
+    assert!(stdout(&out).contains("--algo <name>"));
+    let _ = fs::remove_dir_all(cache_dir("cache"));
+}
+
+#[test]
+fn test_cli_cache() {
+    let dir = cache_dir("cache2");
+    let egypt = |args: &[&str]| egypt_in(&dir, args);
+
+    let out = egypt(&["solve", "problem/02-0.in"]);
+    assert!(!stderr(&out).contains("cache hit"));
+    let sols = stdout(&out);
+
+    // 2 回目はキャッシュから返す。近似解法でも同じ
+    let out = egypt(&["solve", "problem/02-0.in"]);
+    assert!(stderr(&out).contains("cache hit"));
+    assert_eq!(sols, stdout(&out));
+    let out = egypt(&["solve", "--algo", "beam", "problem/02-0.in"]);
+    assert!(stderr(&out).contains("cache hit"));
+    assert_eq!(sols, stdout(&out));
+    let out = egypt(&["solve", "--no-cache", "problem/02-0.in"]);
+    assert!(!stderr(&out).contains("cache hit"));
+
+    // 制限をつけて解いても得られない記録は使わない
+    let out = egypt(&["solve", "--max-depth", "7", "problem/02-0.in"]);
+    assert!(!stderr(&out).contains("cache hit"));
+    assert_eq!("", stdout(&out));
+    let out = egypt(&["solve", "--max-depth", "8", "--format", "json", "problem/02-0.in"]);
+    assert!(stderr(&out).contains("cache hit: ") && stderr(&out).contains(" (solver ida, limits default)"));
+    assert!(stdout(&out).contains(r#""cache":{"solver":"ida","limits":"default"}"#));
+    assert!(!stderr(&egypt(&["solve", "--max-nodes", "1000", "problem/02-0.in"])).contains("cache hit"));
+    let out = egypt(&["solve", "--no-cache", "--format", "json", "problem/02-0.in"]);
+    assert!(stdout(&out).contains(r#""cache":null"#));
+
+    // batch は --cache か --cache-dir を付けたときだけ使う
+    assert!(!stderr(&egypt(&["batch", "problem/02-0.in"])).contains("cache hit"));
+    assert!(stderr(&egypt(&["batch", "--cache", "problem/02-0.in"])).contains("cache hit"));
+    assert!(stderr(&egypt(&["batch", "--cache-dir", &dir, "problem/02-0.in"])).contains("cache hit"));
+
+    // 近似解法や手順を省く設定の結果は記録しない
+    egypt(&["solve", "--algo", "beam", "problem/00-0.in"]);
+    egypt(&["solve", "--reduce", "problem/00-1.in"]);
+    let list = stdout(&egypt(&["cache", "list"]));
+    assert_eq!(1, list.lines().count());
+    assert!(list.contains(" rotate=8 step=12 ") && list.contains(" solver=ida limits=default"));
+
+    let export = stdout(&egypt(&["cache", "export"]));
+    assert!(export.contains("solver ida\n"));
+    assert!(export.contains("# rotate=8 step=12\n"));
+
+    let out = egypt(&["cache", "invalidate", "problem/02-0.in"]);
+    assert!(out.status.success());
+    assert!(stderr(&out).contains("invalidated"));
+    assert_eq!("", stdout(&egypt(&["cache", "list"])));
+    assert!(!stderr(&egypt(&["solve", "problem/02-0.in"])).contains("cache hit"));
+    assert!(egypt(&["cache", "clear"]).status.success());
+    assert!(!egypt(&["cache", "frobnicate"]).status.success());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_cli_server() {
+    let board = fs::read_to_string("problem/02-0.in").unwrap().replace('\n', "\\n");
+    let board_hard = fs::read_to_string("problem/17-5.in").unwrap().replace('\n', "\\n");
+    let board_empty = format!("0 0{}", "\\n........".repeat(8));
+    let requests = [
+        format!(r#"{{"id":1,"method":"moves","board":"{}"}}"#, board),
+        format!(r#"{{"id":2,"method":"solve","board":"{}","options":{{"no-cache":true}}}}"#, board),
+        format!(r#"{{"id":"long","method":"solve","board":"{}","options":{{"algo":"ida","no-cache":true}}}}"#, board_hard),
+        r#"{"id":3,"method":"cancel","target":"long"}"#.to_owned(),
+        r#"{"id":4,"method":"frobnicate"}"#.to_owned(),
+        // 解けている局面では空の解を返す
+        format!(r#"{{"id":5,"method":"solve","board":"{}","options":{{"algo":"genetic","max-len":5}}}}"#, board_empty),
+        format!(r#"{{"id":6,"method":"moves","board":"{}"}}"#, board_empty),
+        "not json".to_owned(),
+    ];
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_egypt"))
+        .arg("server")
+        .env("EGYPT_CACHE_DIR", cache_dir("server"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        for request in &requests {
+            writeln!(stdin, "{}", request).unwrap();
+        }
+    }
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success());
+
+    let lines: Vec<_> = stdout(&out).lines().map(String::from).collect();
+    assert_eq!(requests.len(), lines.len());
+    let response = |id: &str| lines.iter().find(|line| line.starts_with(&format!(r#"{{"id":{},"#, id))).unwrap();
+    assert!(response("1").starts_with(r#"{"id":1,"result":{"moves":[["#));
+    assert!(response("2").contains(r#""termination":"solved""#));
+    assert!(response("2").contains(r#""rotate":8,"#));
+    assert_eq!(r#"{"id":3,"result":{"cancelled":true}}"#, response("3"));
+    assert!(response(r#""long""#).contains(r#""termination":"cancelled""#));
+    assert!(response("4").contains(r#""error":"unknown method: frobnicate""#));
+    assert!(response("null").contains(r#""error":"#));
+    assert!(response("5").contains(r#""termination":"solved""#), "{}", response("5"));
+    assert!(response("5").contains(r#""rotate":0,"#), "{}", response("5"));
+    assert_eq!(r#"{"id":6,"result":{"moves":[],"state":"solved","least_to_solve":null}}"#, response("6"));
+}
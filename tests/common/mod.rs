@@ -0,0 +1,43 @@
+/// 既定のテストで全てのソルバーに解かせる、すぐに解ける部屋
+pub const ROOMS_EASY: &[&str] = &[
+    "00-0",
+    "00-1",
+    "00-2",
+    "00-3",
+    "00-4",
+    "00-5",
+    "01-0",
+    "01-1",
+    "01-2",
+    "01-3",
+    "01-4",
+    "01-5",
+    "02-0",
+    "02-1",
+    "02-2",
+    "02-3",
+    "02-4",
+    "02-5",
+    "03-0",
+    "03-1",
+    "03-4",
+    "04-0",
+    "04-2",
+    "04-3",
+    "04-4",
+    "05-0",
+    "05-1",
+    "05-4",
+    "05-5",
+    "06-4",
+    "06-5",
+    "08-3",
+    "09-0",
+    "09-3",
+    "10-5",
+    "12-0",
+    "13-0",
+    "13-4",
+    "15-0",
+    "17-5",
+];
@@ -0,0 +1,134 @@
+use std::env;
+use std::fs;
+use std::path::{ PathBuf };
+use std::process::{ self, Command };
+
+// Rust の型を C の型に直す
+fn c_type(ty: &str) -> String {
+    let ty = ty.trim();
+    let (qual, base) = if let Some(base) = ty.strip_prefix("*const ") {
+        ("const ", base)
+    } else if let Some(base) = ty.strip_prefix("*mut ") {
+        ("", base)
+    } else {
+        return match ty {
+            "u8"    => "uint8_t",
+            "u32"   => "uint32_t",
+            "i64"   => "int64_t",
+            "usize" => "size_t",
+            "c_int" => "int",
+            ""      => "void",
+            _       => panic!("unknown type in src/ffi.rs: {}", ty),
+        }.to_owned();
+    };
+    let base = match base {
+        "c_char" => "char".to_owned(),
+        "u8"     => "uint8_t".to_owned(),
+        "EgyptBoard" | "EgyptResult" => base.to_owned(),
+        _        => panic!("unknown type in src/ffi.rs: {}", ty),
+    };
+    format!("{}{} *", qual, base)
+}
+
+// "type name" の形にする(ポインタなら "type *name")
+fn c_decl(ty: &str, name: &str) -> String {
+    let ty = c_type(ty);
+    if ty.ends_with('*') { format!("{}{}", ty, name) } else { format!("{} {}", ty, name) }
+}
+
+// src/ffi.rs の extern "C" fn から、C の宣言を作る
+fn rust_declarations(src: &str) -> Vec<String> {
+    let mut decls: Vec<_> = src.match_indices("extern \"C\" fn ")
+        .map(|(i, m)| {
+            let sig = &src[i+m.len()..];
+            let sig = &sig[..sig.find('{').unwrap()];
+            let (open, close) = (sig.find('(').unwrap(), sig.rfind(')').unwrap());
+            let name = &sig[..open];
+            let params: Vec<_> = sig[open+1..close].split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(|p| {
+                    let colon = p.find(':').unwrap();
+                    c_decl(&p[colon+1..], p[..colon].trim())
+                })
+                .collect();
+            let params = if params.is_empty() { "void".to_owned() } else { params.join(", ") };
+            let ret = sig[close+1..].trim();
+            let ret = ret.strip_prefix("->").unwrap_or(ret);
+            format!("{}({});", c_decl(ret, name), params)
+        })
+        .collect();
+    decls.sort();
+    decls
+}
+
+// ヘッダの egypt_xxx(...); の宣言を全て集める
+fn header_declarations(header: &str) -> Vec<String> {
+    let mut decls: Vec<_> = header.lines()
+        .map(str::trim)
+        .filter(|l| l.contains("egypt_") && l.ends_with(");") && !l.starts_with('/') && !l.starts_with('*'))
+        .map(str::to_owned)
+        .collect();
+    decls.sort();
+    decls
+}
+
+#[test]
+fn test_ffi_header() {
+    let src = fs::read_to_string("src/ffi.rs").unwrap();
+    let header = fs::read_to_string("include/egypt.h").unwrap();
+    let exported = rust_declarations(&src);
+    assert!(!exported.is_empty());
+    let declared = header_declarations(&header);
+    for decl in &exported {
+        assert!(declared.contains(decl), "include/egypt.h does not declare: {}", decl);
+    }
+    for decl in &declared {
+        assert!(exported.contains(decl), "src/ffi.rs does not export: {}", decl);
+    }
+
+    // 定数も同じ値で定義されている
+    for line in src.lines().filter(|l| l.starts_with("pub const EGYPT_")) {
+        let name = line["pub const ".len()..line.find(':').unwrap()].trim();
+        let value = line[line.find('=').unwrap()+1..].trim().trim_end_matches(';');
+        let define = format!("#define {} {}", name, value);
+        assert!(header.lines().any(|l| l == define), "include/egypt.h does not have: {}", define);
+    }
+}
+
+// cc と cdylib があれば C のテストプログラムをビルドして実行する
+#[test]
+fn test_ffi_c() {
+    // 実行ファイルと同じディレクトリに cdylib ができる(cargo build が必要)
+    let dir = PathBuf::from(env!("CARGO_BIN_EXE_egypt")).parent().unwrap().to_path_buf();
+    let lib = dir.join(format!("{}egypt{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX));
+    if !lib.exists() {
+        eprintln!("skipped: {} not found (run cargo build first)", lib.display());
+        return;
+    }
+    let exe = env::temp_dir().join(format!("egypt-test-ffi-{}", process::id()));
+    let status = match Command::new(env::var("CC").unwrap_or_else(|_| "cc".to_owned()))
+        .args(["-std=c99", "-Wall", "-Werror", "-Iinclude", "tests/c/test_ffi.c", "-o"])
+        .arg(&exe)
+        .arg(format!("-L{}", dir.display()))
+        .arg("-legypt")
+        .status()
+    {
+        Ok(status) => status,
+        Err(e)     => { eprintln!("skipped: cannot run cc: {}", e); return; },
+    };
+    assert!(status.success());
+
+    let out = Command::new(&exe)
+        .arg("problem/02-0.in")
+        .env("LD_LIBRARY_PATH", &dir)
+        .env("DYLD_LIBRARY_PATH", &dir)
+        .env("EGYPT_CACHE_DIR", env::temp_dir().join(format!("egypt-test-ffi-cache-{}", process::id())))
+        .output()
+        .unwrap();
+    fs::remove_file(&exe).unwrap();
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert!(out.status.success(), "{}{}", stdout, String::from_utf8_lossy(&out.stderr));
+    assert!(stdout.contains("# rotate=8 "));
+    assert!(stdout.ends_with("ok\n"));
+}
@@ -0,0 +1,141 @@
+extern crate egypt;
+
+use std::env;
+use std::fs;
+use std::process;
+use std::str::{ FromStr };
+
+use egypt::board::{ Board };
+use egypt::util;
+
+use egypt::{ Solver };
+use egypt::genetic::{ Encoding, GeneticConfig, GeneticSolver, WeightedFitness };
+use egypt::population::{ Population };
+
+#[test]
+fn test_genetic_seed() {
+    let s = fs::read_to_string("problem/10-5.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+    let config = GeneticConfig {
+        n_cand: 100,
+        ..GeneticConfig::default()
+    };
+
+    let path = env::temp_dir().join(format!("egypt-test-genetic-{}", process::id()));
+    let sols1 = GeneticSolver::new(20, 20).config(config.clone()).seed(42).log(path.clone()).solve(&board).unwrap();
+    let sols2 = GeneticSolver::new(20, 20).config(config).seed(42).solve(&board).unwrap();
+    assert_eq!(sols1, sols2);
+    for sol in &sols1 {
+        assert!(util::verify_solution(&board, sol));
+    }
+
+    // ヘッダ 2 行 + 世代数
+    let log = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(log.starts_with("# seed=42\n"));
+    assert_eq!(2 + 20, log.lines().count());
+}
+
+#[test]
+fn test_genetic_memetic() {
+    let s = fs::read_to_string("problem/10-5.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+    let config = GeneticConfig {
+        n_cand:  100,
+        memetic: true,
+        ..GeneticConfig::default()
+    };
+
+    let sols = GeneticSolver::new(30, 20).config(config).seed(42).solve(&board).unwrap();
+    for sol in &sols {
+        assert!(util::verify_solution(&board, sol));
+        // 局所探索で無駄手は取り除かれている
+        assert_eq!(sol.len(), util::remove_redundant(&board, sol).len());
+    }
+}
+
+#[test]
+fn test_genetic_fitness() {
+    let s = fs::read_to_string("problem/10-5.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+    let config = GeneticConfig {
+        n_cand: 100,
+        ..GeneticConfig::default()
+    };
+
+    // 負の評価値もルーレット選択で扱える
+    let sols = GeneticSolver::new(20, 20).config(config.clone()).fitness(WeightedFitness::default()).seed(42).solve(&board).unwrap();
+    for sol in &sols {
+        assert!(util::verify_solution(&board, sol));
+    }
+
+    // 評価値が NaN になる重みでも panic しない
+    let fitness = WeightedFitness {
+        walk: f64::NAN,
+        ..WeightedFitness::default()
+    };
+    let sols = GeneticSolver::new(20, 20).config(config).fitness(fitness).seed(42).solve(&board).unwrap();
+    for sol in &sols {
+        assert!(util::verify_solution(&board, sol));
+    }
+}
+
+#[test]
+fn test_genetic_encoding() {
+    let s = fs::read_to_string("problem/10-5.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+    let config = GeneticConfig {
+        n_cand:   100,
+        memetic:  true,
+        encoding: Encoding::Absolute,
+        ..GeneticConfig::default()
+    };
+
+    let sols = GeneticSolver::new(30, 20).config(config).seed(42).solve(&board).unwrap();
+    for sol in &sols {
+        assert!(util::verify_solution(&board, sol));
+    }
+}
+
+#[test]
+fn test_genetic_population() {
+    let s = fs::read_to_string("problem/10-5.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+    let config = GeneticConfig {
+        n_cand: 100,
+        ..GeneticConfig::default()
+    };
+
+    let mut solver = GeneticSolver::new(30, 20).config(config.clone()).seed(42);
+    let sols = solver.solve(&board).unwrap();
+    assert!(!sols.is_empty());
+
+    // 保存した集団から始めると、最終世代の解がそのまま得られる
+    let path = env::temp_dir().join(format!("egypt-test-population-{}", process::id()));
+    solver.population().save(&path).unwrap();
+    let pop = Population::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let sols2 = GeneticSolver::new(30, 0).config(config.clone()).initial_population(pop).solve(&board).unwrap();
+    assert_eq!(sols, sols2);
+
+    // 既知の解は別の符号化でも初期集団に入る
+    let config = GeneticConfig {
+        encoding: Encoding::Absolute,
+        ..config
+    };
+    let sols3 = GeneticSolver::new(30, 0).config(config).initial_solutions(sols.clone()).solve(&board).unwrap();
+    for sol in &sols {
+        assert!(sols3.contains(sol));
+    }
+}
+
+#[test]
+fn test_genetic_trivial() {
+    // 解けている局面は空の解、打てる手が無い局面は解無し
+    let solved = Board::from_str(&format!("0 0{}", "\n........".repeat(8))).unwrap();
+    let no_move = Board::from_str(&format!("0 0\n.1....1.{}", "\n........".repeat(7))).unwrap();
+    let cases: Vec<(Board, Vec<Vec<u8>>)> = vec![(solved, vec![vec![]]), (no_move, vec![])];
+    for (board, expected) in cases {
+        assert_eq!(expected, GeneticSolver::new(5, 10).seed(1).solve(&board).unwrap());
+    }
+}
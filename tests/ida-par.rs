@@ -0,0 +1,54 @@
+extern crate egypt;
+
+mod common;
+
+use std::fs;
+use std::path;
+use std::str::{ FromStr };
+
+use egypt::board::{ Board };
+
+use egypt::{ Solver };
+use egypt::ida::{ IdaSolver };
+
+fn read_board(room: &str) -> Board {
+    let path = path::Path::new("problem/").join(format!("{}.in", room));
+    eprintln!("{}", path.to_str().unwrap());
+    let s = fs::read_to_string(path).unwrap();
+    Board::from_str(&s).unwrap()
+}
+
+#[test]
+fn test_ida_par() {
+    const MAX_DEPTH_INI:      u32 = 0;
+    const MAX_NODE_COUNT_IDA: u64 = 1_000_000_000_000_000_000;
+
+    let mut solver_ida     = IdaSolver::new(MAX_DEPTH_INI, MAX_NODE_COUNT_IDA);
+    let mut solver_ida_par = IdaSolver::new(MAX_DEPTH_INI, MAX_NODE_COUNT_IDA).threads(4);
+
+    for room in common::ROOMS_EASY {
+        let board = read_board(room);
+
+        // 並列版は直列版と同じ順序で解を返す
+        let sols_ida = solver_ida.solve(&board).unwrap();
+        let sols_ida_par = solver_ida_par.solve(&board).unwrap();
+        assert_eq!(sols_ida, sols_ida_par);
+    }
+}
+
+#[test]
+fn test_ida_par_limit() {
+    // ノード数の上限に達するかどうかも直列版と同じ
+    for &max_node_count in &[100, 1_000, 10_000, 100_000] {
+        for room in &["02-0", "05-0", "13-0", "17-5"] {
+            let board = read_board(room);
+            let r     = IdaSolver::new(0, max_node_count).solve(&board);
+            let r_par = IdaSolver::new(0, max_node_count).threads(4).solve(&board);
+            match (r, r_par) {
+                (Ok(sols), Ok(sols_par)) => assert_eq!(sols, sols_par),
+                (Err(e), Err(e_par))     => assert_eq!(e.kind(), e_par.kind()),
+                (r, r_par)               => panic!("{}: {:?} != {:?}", room, r, r_par),
+            }
+        }
+    }
+}
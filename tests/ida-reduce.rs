@@ -0,0 +1,38 @@
+extern crate egypt;
+
+mod common;
+
+use std::fs;
+use std::path;
+use std::str::{ FromStr };
+
+use egypt::board::{ Board };
+
+use egypt::{ Solver };
+use egypt::ida::{ IdaSolver };
+
+#[test]
+fn test_ida_reduce() {
+    const MAX_DEPTH_INI:      u32 = 0;
+    const MAX_NODE_COUNT_IDA: u64 = 1_000_000_000_000_000_000;
+
+    let mut solver_ida         = IdaSolver::new(MAX_DEPTH_INI, MAX_NODE_COUNT_IDA);
+    let mut solver_ida_red     = IdaSolver::new(MAX_DEPTH_INI, MAX_NODE_COUNT_IDA).reduce_order(true);
+    let mut solver_ida_red_par = IdaSolver::new(MAX_DEPTH_INI, MAX_NODE_COUNT_IDA).reduce_order(true).threads(4);
+
+    for room in common::ROOMS_EASY {
+        let path = path::Path::new("problem/").join(format!("{}.in", room));
+        eprintln!("{}", path.to_str().unwrap());
+        let s = fs::read_to_string(path).unwrap();
+        let board = Board::from_str(&s).unwrap();
+
+        let mut sols_ida = solver_ida.solve(&board).unwrap();
+        sols_ida.sort();
+        // 手順の入れ替えで省いた解も復元される
+        let mut sols_ida_red = solver_ida_red.solve(&board).unwrap();
+        let sols_ida_red_par = solver_ida_red_par.solve(&board).unwrap();
+        assert_eq!(sols_ida_red, sols_ida_red_par);
+        sols_ida_red.sort();
+        assert_eq!(sols_ida, sols_ida_red);
+    }
+}
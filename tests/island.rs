@@ -0,0 +1,43 @@
+extern crate egypt;
+
+use std::fs;
+use std::str::{ FromStr };
+
+use egypt::board::{ Board };
+use egypt::util;
+
+use egypt::{ Solver };
+use egypt::genetic::{ GeneticConfig };
+use egypt::island::{ IslandSolver, Topology };
+
+#[test]
+fn test_island() {
+    let s = fs::read_to_string("problem/10-5.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+    let config = GeneticConfig {
+        n_cand: 100,
+        ..GeneticConfig::default()
+    };
+
+    for &topology in &[Topology::Ring, Topology::Complete] {
+        // スレッドの実行順によらず、シードが同じなら同じ結果になる
+        let sols1 = IslandSolver::new(30, 20, 3).config(config.clone()).migration(5, 5).topology(topology).seed(42).solve(&board).unwrap();
+        let sols2 = IslandSolver::new(30, 20, 3).config(config.clone()).migration(5, 5).topology(topology).seed(42).solve(&board).unwrap();
+        assert_eq!(sols1, sols2);
+        for sol in &sols1 {
+            assert!(util::verify_solution(&board, sol));
+            assert_eq!(sols1[0].len(), sol.len());
+        }
+    }
+}
+
+#[test]
+fn test_island_trivial() {
+    // 解けている局面は空の解、打てる手が無い局面は解無し
+    let solved = Board::from_str(&format!("0 0{}", "\n........".repeat(8))).unwrap();
+    let no_move = Board::from_str(&format!("0 0\n.1....1.{}", "\n........".repeat(7))).unwrap();
+    let cases: Vec<(Board, Vec<Vec<u8>>)> = vec![(solved, vec![vec![]]), (no_move, vec![])];
+    for (board, expected) in cases {
+        assert_eq!(expected, IslandSolver::new(5, 10, 2).seed(1).solve(&board).unwrap());
+    }
+}
@@ -0,0 +1,148 @@
+extern crate egypt;
+
+mod common;
+
+use std::fs;
+use std::path;
+use std::str::{ FromStr };
+
+use egypt::board::{ Board };
+use egypt::known::{ self, Known };
+use egypt::util;
+
+use egypt::{ Solver, SolverError };
+use egypt::beam::{ BeamSolver };
+use egypt::bfs::{ BfsSolver };
+use egypt::bnb::{ BnbSolver };
+use egypt::ida::{ IdaSolver };
+use egypt::iddfs::{ IddfsSolver };
+
+const MAX_NODE_COUNT: u64 = 1_000_000_000_000_000_000;
+
+// 最短が分かっていない部屋を ignored のテストで探索するノード数
+const MAX_NODE_COUNT_UPPER: u64 = 100_000_000;
+
+// 全ての部屋を記録と共に読む
+fn load_rooms() -> Vec<(Known, Board)> {
+    known::load(known::KNOWN_PATH).unwrap()
+        .into_iter()
+        .map(|k| {
+            let path = path::Path::new("problem/").join(format!("{}.in", k.room));
+            let s = fs::read_to_string(path).unwrap();
+            let board = Board::from_str(&s).unwrap();
+            (k, board)
+        })
+        .collect()
+}
+
+// 記録の解をそのまま返す(分枝限定法の上界に使う)
+struct KnownSolver(Vec<u8>);
+
+impl Solver for KnownSolver {
+    fn solve(&mut self, _board: &Board) -> Result<Vec<Vec<u8>>,SolverError> {
+        Ok(vec![self.0.clone()])
+    }
+}
+
+// 最短が分かっていない部屋を、記録の解を上界とした分枝限定法で max_node_count まで探索する
+// 得られる解は上界を超えない。探索を終えたら最短が分かったので、記録を optimal にすべきである
+fn check_upper(k: &Known, board: &Board, max_node_count: u64) {
+    let mut solver = BnbSolver::new(max_node_count).upper_bound_solver(KnownSolver(k.solution.clone()));
+    let r = solver.solve(board);
+    let finished = r.is_ok();
+    let sols = r.unwrap_or_else(|e| e.solutions().to_vec());
+    assert!(!sols.is_empty(), "{}: no solution", k.room);
+    assert!(sols.iter().all(|sol| util::verify_solution(board, sol)), "{}: invalid solution", k.room);
+    assert!(sols.iter().all(|sol| sol.len() as u32 <= k.rotate), "{}: exceeds the upper bound", k.room);
+    if finished {
+        eprintln!("{}: proved optimal at rotate {}; record it as optimal", k.room, sols[0].len());
+    }
+}
+
+// 解の回転数と最小の歩数が記録と一致するか
+fn check_optimal(k: &Known, board: &Board, sols: &[Vec<u8>]) {
+    assert!(!sols.is_empty(), "{}: no solution", k.room);
+    assert!(sols.iter().all(|sol| sol.len() as u32 == k.rotate), "{}: rotate", k.room);
+    assert!(sols.iter().all(|sol| util::verify_solution(board, sol)), "{}: invalid solution", k.room);
+    let step = sols.iter().map(|sol| util::step_of_solution(board, sol)).min().unwrap();
+    assert_eq!(k.step, step, "{}: step", k.room);
+}
+
+#[test]
+fn test_known_file() {
+    let rooms = load_rooms();
+
+    // 全ての問題がちょうど 1 回ずつ、名前順に記録されている
+    let mut names: Vec<_> = fs::read_dir("problem/").unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(".in"))
+        .map(|name| name.trim_end_matches(".in").to_owned())
+        .collect();
+    names.sort();
+    let rooms_names: Vec<_> = rooms.iter().map(|(k, _)| k.room.clone()).collect();
+    assert_eq!(names, rooms_names);
+
+    for (k, board) in &rooms {
+        assert!(util::verify_solution(board, &k.solution), "{}: invalid solution", k.room);
+        assert_eq!(k.step, util::step_of_solution(board, &k.solution), "{}: step", k.room);
+        assert!(board.least_to_solve() <= k.rotate, "{}: below the lower bound", k.room);
+
+        // 最短解から手を抜いたものは解ではない
+        if k.is_optimal() && !k.solution.is_empty() {
+            let n = k.solution.len();
+            assert!(!util::verify_solution(board, &k.solution[..n-1]), "{}", k.room);
+            assert!(!util::verify_solution(board, &k.solution[1..]), "{}", k.room);
+        }
+    }
+}
+
+#[test]
+fn test_known_easy() {
+    let rooms = load_rooms();
+    for name in common::ROOMS_EASY {
+        let (k, board) = rooms.iter().find(|(k, _)| k.room == *name).unwrap();
+        assert!(k.is_optimal());
+        eprintln!("{}", k.room);
+
+        check_optimal(k, board, &BfsSolver::new(10_000_000).solve(board).unwrap());
+        check_optimal(k, board, &IddfsSolver::new(0, MAX_NODE_COUNT).solve(board).unwrap());
+        check_optimal(k, board, &IdaSolver::new(0, MAX_NODE_COUNT).solve(board).unwrap());
+        check_optimal(k, board, &IdaSolver::new(0, MAX_NODE_COUNT).reduce_order(true).solve(board).unwrap());
+        check_optimal(k, board, &BnbSolver::new(MAX_NODE_COUNT).solve(board).unwrap());
+    }
+}
+
+#[test]
+fn test_known_upper() {
+    for (k, board) in &load_rooms() {
+        if k.is_optimal() { continue; }
+        check_upper(k, board, 10_000);
+    }
+}
+
+// 時間がかかるので cargo test --release -- --ignored で実行する
+#[test]
+#[ignore]
+fn test_known_all() {
+    for (k, board) in &load_rooms() {
+        eprintln!("{}", k.room);
+
+        // 近似解法は最短より短い解を返さない
+        let sols = BeamSolver::new(1000, 100).solve(board).unwrap();
+        assert!(sols.iter().all(|sol| util::verify_solution(board, sol)), "{}: invalid solution", k.room);
+        if let Some(sol) = sols.first() {
+            if k.is_optimal() {
+                assert!(sol.len() as u32 >= k.rotate, "{}: beam beats the optimum", k.room);
+            }
+        }
+
+        if !k.is_optimal() {
+            check_upper(k, board, MAX_NODE_COUNT_UPPER);
+            continue;
+        }
+        let mut solver = IdaSolver::new(0, MAX_NODE_COUNT).reduce_order(true).verbose(false);
+        check_optimal(k, board, &solver.solve(board).unwrap());
+        let mut solver = BnbSolver::new(MAX_NODE_COUNT);
+        check_optimal(k, board, &solver.solve(board).unwrap());
+    }
+}
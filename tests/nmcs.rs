@@ -0,0 +1,35 @@
+extern crate egypt;
+
+use std::fs;
+use std::path;
+use std::str::{ FromStr };
+
+use egypt::board::{ Board };
+use egypt::util;
+
+use egypt::{ Solver };
+use egypt::nmcs::{ NmcsSolver };
+
+#[test]
+fn test_nmcs() {
+    const PROBLEMS: &[&str] = &[
+        "00-0.in",
+        "02-4.in",
+        "10-5.in",
+    ];
+
+    for filename in PROBLEMS {
+        let path = path::Path::new("problem/").join(filename);
+        let s = fs::read_to_string(path).unwrap();
+        let board = Board::from_str(&s).unwrap();
+
+        let sols = NmcsSolver::new(1, 3).seed(12345).max_len(40).solve(&board).unwrap();
+        for sol in &sols {
+            assert!(util::verify_solution(&board, sol));
+        }
+
+        // 同じシードなら同じ結果
+        let sols2 = NmcsSolver::new(1, 3).seed(12345).max_len(40).solve(&board).unwrap();
+        assert_eq!(sols, sols2);
+    }
+}
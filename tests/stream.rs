@@ -0,0 +1,45 @@
+extern crate egypt;
+
+use std::fs;
+use std::str::{ FromStr };
+
+use egypt::board::{ Board };
+
+use egypt::{ Solutions, Solver, StreamSolver };
+use egypt::bfs::{ BfsSolver };
+use egypt::ida::{ IdaSolver };
+use egypt::iddfs::{ IddfsSolver };
+
+#[test]
+fn test_stream() {
+    let s = fs::read_to_string("problem/00-1.in").unwrap();
+    let board = Board::from_str(&s).unwrap();
+
+    let mut sols = IdaSolver::new(0, 1_000_000_000).solve(&board).unwrap();
+    sols.sort();
+    assert!(sols.len() > 1);
+
+    // 全ての解が、見つかった深さと共に返される
+    let mut sols_stream: Vec<_> = Solutions::new(IddfsSolver::new(0, 1_000_000_000), &board)
+        .map(|(depth, sol)| {
+            assert_eq!(depth as usize, sol.len());
+            sol
+        })
+        .collect();
+    sols_stream.sort();
+    assert_eq!(sols, sols_stream);
+
+    // 途中で打ち切れる
+    let mut it = Solutions::new(IdaSolver::new(0, 1_000_000_000), &board);
+    let (_, sol) = it.next().unwrap();
+    assert!(sols.contains(&sol));
+    assert!(it.finish().is_ok());
+
+    let mut n = 0;
+    let r = BfsSolver::new(10_000_000).solve_stream(&board, &mut |_, _| {
+        n += 1;
+        n < 3
+    });
+    assert!(r.is_ok());
+    assert_eq!(3, n);
+}